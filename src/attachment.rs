@@ -0,0 +1,145 @@
+use std::{
+    any::{type_name, Any},
+    fmt::{self, Display},
+};
+
+use crate::{remote::RemoteAttachment, Error};
+
+impl Error {
+    /// Hang an arbitrary value on the error, to be looked up later with
+    /// [`request_ref`](Self::request_ref). It is not shown in the report.
+    pub fn attach<A>(self, attachment: A) -> Self
+    where
+        A: Any + Send + Sync,
+    {
+        self.with_attachment(Attachment {
+            value: Box::new(attachment),
+            type_name: type_name::<A>(),
+            display: None,
+        })
+    }
+
+    /// Like [`attach`](Self::attach), but the value is also listed under
+    /// "Attachments:" in the report.
+    pub fn attach_printable<A>(self, attachment: A) -> Self
+    where
+        A: Any + Display + Send + Sync,
+    {
+        self.with_attachment(Attachment {
+            value: Box::new(attachment),
+            type_name: type_name::<A>(),
+            display: Some(display::<A>),
+        })
+    }
+
+    /// The most recently attached `A`, searching every layer of the error.
+    pub fn request_ref<A>(&self) -> Option<&A>
+    where
+        A: Any,
+    {
+        self.request_all::<A>().next()
+    }
+
+    /// Every attached `A`, most recently attached first.
+    pub fn request_all<A>(&self) -> impl Iterator<Item = &A>
+    where
+        A: Any,
+    {
+        let mut attachments: Vec<&A> = self
+            .attachments()
+            .filter_map(|attachment| attachment.value.downcast_ref())
+            .collect();
+        attachments.reverse();
+        attachments.into_iter()
+    }
+}
+
+pub(crate) struct Attachment {
+    value: Box<dyn Any + Send + Sync>,
+    type_name: &'static str,
+    display: Option<fn(&(dyn Any + Send + Sync), &mut fmt::Formatter<'_>) -> fmt::Result>,
+}
+
+impl Attachment {
+    // An attachment that came from another process, carried over by type name
+    // and display only.
+    pub(crate) fn remote(attachment: RemoteAttachment) -> Self {
+        let printable = attachment.display().is_some();
+        Attachment {
+            value: Box::new(attachment),
+            type_name: type_name::<RemoteAttachment>(),
+            display: if printable {
+                Some(display::<RemoteAttachment>)
+            } else {
+                None
+            },
+        }
+    }
+
+    pub(crate) fn type_name(&self) -> &str {
+        match self.value.downcast_ref::<RemoteAttachment>() {
+            Some(remote) => remote.type_name(),
+            None => self.type_name,
+        }
+    }
+
+    pub(crate) fn is_printable(&self) -> bool {
+        self.display.is_some()
+    }
+}
+
+impl Display for Attachment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.display {
+            Some(display) => display(&*self.value, f),
+            None => Ok(()),
+        }
+    }
+}
+
+fn display<A>(value: &(dyn Any + Send + Sync), f: &mut fmt::Formatter<'_>) -> fmt::Result
+where
+    A: Any + Display,
+{
+    match value.downcast_ref::<A>() {
+        Some(value) => Display::fmt(value, f),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Error;
+
+    #[test]
+    fn request_all_returns_the_most_recent_first_across_layers() {
+        let error = Error::msg("root")
+            .attach(1u8)
+            .attach("not a number")
+            .context("outer")
+            .attach(2u8)
+            .attach(3u8);
+
+        assert_eq!(
+            error.request_all::<u8>().copied().collect::<Vec<_>>(),
+            [3, 2, 1]
+        );
+        assert_eq!(error.request_ref::<u8>(), Some(&3));
+        assert_eq!(error.request_ref::<&str>(), Some(&"not a number"));
+        assert_eq!(error.request_ref::<u16>(), None);
+    }
+
+    #[test]
+    fn only_printable_attachments_are_listed_in_order() {
+        let error = Error::msg("root")
+            .attach_printable("first")
+            .attach(7u32)
+            .context("outer")
+            .attach_printable("second\nline");
+
+        let report = format!("{:?}", error);
+        let report = report.split("\n\nStack backtrace:").next().unwrap();
+        let attachments = &report[report.find("Attachments:").unwrap()..];
+        assert_eq!(attachments, "Attachments:\n    first\n    second\n    line");
+    }
+}
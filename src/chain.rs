@@ -0,0 +1,85 @@
+use std::{error::Error as StdError, vec};
+
+/// Iterator over every layer of an [`Error`](crate::Error), outermost first.
+///
+/// Context layers come first, followed by the wrapped error and each of its
+/// `source()`s.
+#[derive(Clone)]
+pub struct Chain<'a> {
+    state: ChainState<'a>,
+}
+
+#[derive(Clone)]
+enum ChainState<'a> {
+    Linked {
+        next: Option<&'a (dyn StdError + 'static)>,
+    },
+    Buffered {
+        rest: vec::IntoIter<&'a (dyn StdError + 'static)>,
+    },
+}
+
+impl<'a> Chain<'a> {
+    pub(crate) fn new(head: &'a (dyn StdError + 'static)) -> Self {
+        Self {
+            state: ChainState::Linked { next: Some(head) },
+        }
+    }
+}
+
+impl<'a> Iterator for Chain<'a> {
+    type Item = &'a (dyn StdError + 'static);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match &mut self.state {
+            ChainState::Linked { next } => {
+                let error = (*next)?;
+                *next = error.source();
+                Some(error)
+            }
+            ChainState::Buffered { rest } => rest.next(),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl DoubleEndedIterator for Chain<'_> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match &mut self.state {
+            // Walking backwards needs the whole chain up front, since `source()`
+            // only links forwards.
+            ChainState::Linked { mut next } => {
+                let mut rest = Vec::new();
+                while let Some(cause) = next {
+                    next = cause.source();
+                    rest.push(cause);
+                }
+                let mut rest = rest.into_iter();
+                let last = rest.next_back();
+                self.state = ChainState::Buffered { rest };
+                last
+            }
+            ChainState::Buffered { rest } => rest.next_back(),
+        }
+    }
+}
+
+impl ExactSizeIterator for Chain<'_> {
+    fn len(&self) -> usize {
+        match &self.state {
+            ChainState::Linked { mut next } => {
+                let mut len = 0;
+                while let Some(cause) = next {
+                    next = cause.source();
+                    len += 1;
+                }
+                len
+            }
+            ChainState::Buffered { rest } => rest.len(),
+        }
+    }
+}
@@ -0,0 +1,118 @@
+use std::{
+    error::Error as StdError,
+    fmt::{self, Debug, Display},
+};
+
+use crate::Error;
+
+/// An [`Error`] that implements `std::error::Error`, so it can be nested in
+/// other error types or passed to APIs that expect `impl Error`.
+///
+/// `Display` and `Debug` match the wrapped `Error`, and `source()` walks every
+/// context layer. Converting back with `Error::from` unwraps it rather than
+/// boxing it a second time.
+pub struct Compat(Error);
+
+impl Compat {
+    pub fn into_inner(self) -> Error {
+        self.0
+    }
+}
+
+impl Error {
+    pub fn into_std(self) -> Compat {
+        Compat(self)
+    }
+}
+
+impl AsRef<Error> for Compat {
+    fn as_ref(&self) -> &Error {
+        &self.0
+    }
+}
+
+impl AsMut<Error> for Compat {
+    fn as_mut(&mut self) -> &mut Error {
+        &mut self.0
+    }
+}
+
+impl Display for Compat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.display(f)
+    }
+}
+
+impl Debug for Compat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.debug(f)
+    }
+}
+
+impl StdError for Compat {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.0.source()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error as StdError, io};
+
+    use crate::{regardless, Error};
+
+    fn error() -> Error {
+        Error::new(io::Error::other("root"))
+            .context("middle")
+            .context("outer")
+    }
+
+    // The address of the outermost layer, which only stays the same if no
+    // layer was added or rebuilt.
+    fn address(error: &Error) -> *const () {
+        &**error as *const (dyn StdError + Send + Sync) as *const ()
+    }
+
+    #[test]
+    fn compat_round_trip_is_lossless() {
+        let error = error();
+        let (address_before, chain, alternate) = (
+            address(&error),
+            error.chain().count(),
+            format!("{:#}", error),
+        );
+        let backtrace = error.backtrace().to_string();
+
+        let compat = error.into_std();
+        assert_eq!(
+            compat.source().map(ToString::to_string).as_deref(),
+            Some("middle")
+        );
+        let error = Error::from(compat);
+
+        assert_eq!(address(&error), address_before);
+        assert_eq!(error.chain().count(), chain);
+        assert_eq!(format!("{:#}", error), alternate);
+        assert_eq!(error.backtrace().to_string(), backtrace);
+    }
+
+    #[test]
+    fn boxed_round_trip_is_lossless() {
+        let error = error();
+        let (address_before, chain, alternate) = (
+            address(&error),
+            error.chain().count(),
+            format!("{:#}", error),
+        );
+        let backtrace = error.backtrace().to_string();
+
+        let boxed: Box<dyn StdError + Send + Sync> = error.into();
+        assert_eq!(boxed.to_string(), "outer");
+        let error = regardless!(boxed);
+
+        assert_eq!(address(&error), address_before);
+        assert_eq!(error.chain().count(), chain);
+        assert_eq!(format!("{:#}", error), alternate);
+        assert_eq!(error.backtrace().to_string(), backtrace);
+    }
+}
@@ -0,0 +1,264 @@
+use std::fmt::Debug;
+
+use crate::Error;
+
+// Autoref specialisation: `(&DebugIfAble(x)).__regardless_debug()` picks
+// `RenderDebug` when `T: Debug` and falls back to `RenderNone` otherwise, so
+// `ensure!(a == b)` still compiles for operands that are not `Debug`.
+pub struct DebugIfAble<'a, T: ?Sized>(pub &'a T);
+
+pub trait RenderDebug {
+    fn __regardless_debug(&self) -> Option<String>;
+}
+
+impl<T> RenderDebug for DebugIfAble<'_, T>
+where
+    T: Debug + ?Sized,
+{
+    fn __regardless_debug(&self) -> Option<String> {
+        Some(format!("{:?}", self.0))
+    }
+}
+
+pub trait RenderNone {
+    fn __regardless_debug(&self) -> Option<String>;
+}
+
+impl<T> RenderNone for &DebugIfAble<'_, T>
+where
+    T: ?Sized,
+{
+    fn __regardless_debug(&self) -> Option<String> {
+        None
+    }
+}
+
+#[cold]
+#[track_caller]
+pub fn ensure_failed(condition: &'static str, lhs: Option<String>, rhs: Option<String>) -> Error {
+    match (lhs, rhs) {
+        (Some(lhs), Some(rhs)) => Error::msg(format!("{} ({} vs {})", condition, lhs, rhs)),
+        _ => Error::msg(condition),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{ensure, Result};
+
+    fn check(f: impl FnOnce() -> Result<()>) -> String {
+        f().expect_err("condition should fail").to_string()
+    }
+
+    #[test]
+    fn every_operator_shows_both_operands() {
+        let (a, b) = (1, 2);
+        assert_eq!(
+            check(|| {
+                ensure!(a == b);
+                Ok(())
+            }),
+            "Condition failed: `a == b` (1 vs 2)"
+        );
+        assert_eq!(
+            check(|| {
+                ensure!(a != a);
+                Ok(())
+            }),
+            "Condition failed: `a != a` (1 vs 1)"
+        );
+        assert_eq!(
+            check(|| {
+                ensure!(b < a);
+                Ok(())
+            }),
+            "Condition failed: `b < a` (2 vs 1)"
+        );
+        assert_eq!(
+            check(|| {
+                ensure!(b <= a);
+                Ok(())
+            }),
+            "Condition failed: `b <= a` (2 vs 1)"
+        );
+        assert_eq!(
+            check(|| {
+                ensure!(a > b);
+                Ok(())
+            }),
+            "Condition failed: `a > b` (1 vs 2)"
+        );
+        assert_eq!(
+            check(|| {
+                ensure!(a >= b);
+                Ok(())
+            }),
+            "Condition failed: `a >= b` (1 vs 2)"
+        );
+        assert!(check(|| {
+            ensure!(a + 1 == b * 2);
+            Ok(())
+        })
+        .ends_with("(2 vs 4)"));
+    }
+
+    #[test]
+    fn passing_condition_returns_ok() {
+        let run = || -> Result<()> {
+            ensure!(1 + 1 == 2);
+            ensure!("a" < "b");
+            Ok(())
+        };
+        assert!(run().is_ok());
+    }
+
+    #[test]
+    fn operands_without_debug_leave_out_the_values() {
+        struct NoDebug(u8);
+        impl PartialEq for NoDebug {
+            fn eq(&self, other: &Self) -> bool {
+                self.0 == other.0
+            }
+        }
+
+        let message = check(|| {
+            ensure!(NoDebug(1) == NoDebug(2));
+            Ok(())
+        });
+        assert_eq!(message, "Condition failed: `NoDebug(1) == NoDebug(2)`");
+    }
+
+    #[test]
+    fn generics_and_boolean_operators_fall_back() {
+        let v = [1u8];
+        let message = check(|| {
+            ensure!(Vec::<u8>::new().len() == v.len());
+            Ok(())
+        });
+        assert_eq!(
+            message,
+            "Condition failed: `Vec::<u8>::new().len() == v.len()`"
+        );
+
+        let message = check(|| {
+            ensure!(v.is_empty() && v[0] == 0);
+            Ok(())
+        });
+        assert_eq!(message, "Condition failed: `v.is_empty() && v[0] == 0`");
+
+        let message = check(|| {
+            ensure!(v[0] == 0 || v.is_empty());
+            Ok(())
+        });
+        assert_eq!(message, "Condition failed: `v[0] == 0 || v.is_empty()`");
+    }
+
+    #[test]
+    fn trailing_comma_is_accepted() {
+        let (a, b) = (1, 2);
+        assert_eq!(
+            check(|| {
+                ensure!(a == b,);
+                Ok(())
+            }),
+            "Condition failed: `a == b` (1 vs 2)"
+        );
+        assert_eq!(
+            check(|| {
+                ensure!(a > b,);
+                Ok(())
+            }),
+            "Condition failed: `a > b` (1 vs 2)"
+        );
+        let t = false;
+        assert_eq!(
+            check(|| {
+                ensure!(t,);
+                Ok(())
+            }),
+            "Condition failed: `t`"
+        );
+    }
+
+    #[test]
+    fn long_condition_falls_back_instead_of_failing_to_compile() {
+        let a = 1;
+        let message = check(|| {
+            ensure!(
+                a + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    + a
+                    == 1
+            );
+            Ok(())
+        });
+        assert!(message.starts_with("Condition failed: `a + a"));
+        assert!(!message.ends_with(')'));
+    }
+}
@@ -0,0 +1,1176 @@
+use std::{
+    any::{type_name, Any, TypeId},
+    backtrace::{Backtrace, BacktraceStatus},
+    error::Error as StdError,
+    fmt::{self, Debug, Display},
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+    panic::Location,
+    ptr::{self, NonNull},
+};
+
+use crate::{
+    attachment::Attachment,
+    compat::Compat,
+    context::ContextError,
+    fmt::NestedReports,
+    hook::{self, ReportHandler},
+    ptr::{Mut, Own, Ref},
+    remote::RemoteError,
+    scope,
+    section::{Heading, ReportSection},
+    wrapper::{BoxedError, DisplayError, MessageError},
+    Error,
+};
+
+// Everything behind the single pointer held by `Error`. The header is shared by
+// every instantiation, so an `ErrorImpl<E>` can be handled as an `ErrorImpl<()>`
+// until the vtable recovers the real `E`.
+#[repr(C)]
+pub(crate) struct ErrorImpl<E = ()> {
+    vtable: &'static ErrorVTable,
+    // Where this layer was created.
+    location: &'static Location<'static>,
+    // Context layers around another `Error` leave these empty and defer to the
+    // inner error.
+    backtrace: Option<Backtrace>,
+    handler: Option<Box<dyn ReportHandler>>,
+    exit_code: Option<u8>,
+    // Set on the outermost layer added by the scopes, so that an error built
+    // around this one does not get them a second time.
+    scoped: bool,
+    // Notes, sections and attachments added to this layer, in the order they
+    // were added.
+    sections: Vec<ReportSection>,
+    attachments: Vec<Attachment>,
+    _object: E,
+}
+
+struct ErrorVTable {
+    object_drop: unsafe fn(Own<ErrorImpl>),
+    object_ref: unsafe fn(Ref<'_, ErrorImpl>) -> &(dyn StdError + Send + Sync + 'static),
+    object_mut: unsafe fn(Mut<'_, ErrorImpl>) -> &mut (dyn StdError + Send + Sync + 'static),
+    object_downcast: unsafe fn(Ref<'_, ErrorImpl>, TypeId) -> Option<Ref<'_, ()>>,
+    object_downcast_mut: unsafe fn(Mut<'_, ErrorImpl>, TypeId) -> Option<Mut<'_, ()>>,
+    object_drop_rest: unsafe fn(Own<ErrorImpl>, TypeId),
+    object_inner: unsafe fn(Ref<'_, ErrorImpl>) -> Option<&Error>,
+    // The type names of the context value and of the wrapped error, for the
+    // parts of the chain this layer holds.
+    context_type: Option<fn() -> &'static str>,
+    error_type: Option<fn() -> &'static str>,
+    // The context value as the nested reports it renders, for a layer added
+    // by `context_with_reports`.
+    context_reports: Option<unsafe fn(Ref<'_, ErrorImpl>) -> &dyn NestedReports>,
+}
+
+impl Error {
+    #[track_caller]
+    pub(crate) fn from_std<E>(error: E) -> Self
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<E>,
+            object_ref: object_ref::<E>,
+            object_mut: object_mut::<E>,
+            object_downcast: object_downcast::<E>,
+            object_downcast_mut: object_downcast_mut::<E>,
+            object_drop_rest: object_drop_front::<E>,
+            object_inner: no_inner,
+            context_type: None,
+            error_type: Some(type_name::<E>),
+            context_reports: None,
+        };
+
+        let backtrace = backtrace_if_absent(&error);
+        let handler = hook::capture_handler(&error);
+
+        // SAFETY: the vtable was built for `E`.
+        let error = unsafe { Error::construct(error, vtable, backtrace, handler) };
+        scope::apply(error)
+    }
+
+    // A chain rebuilt from another process. It already says where it failed,
+    // so no local backtrace is captured, and neither the hook nor the scopes
+    // of this thread apply to it.
+    #[track_caller]
+    pub(crate) fn from_remote(error: RemoteError) -> Self {
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<RemoteError>,
+            object_ref: object_ref::<RemoteError>,
+            object_mut: object_mut::<RemoteError>,
+            object_downcast: object_downcast::<RemoteError>,
+            object_downcast_mut: object_downcast_mut::<RemoteError>,
+            object_drop_rest: object_drop_front::<RemoteError>,
+            object_inner: no_inner,
+            context_type: None,
+            error_type: Some(type_name::<RemoteError>),
+            context_reports: None,
+        };
+
+        // SAFETY: the vtable was built for `RemoteError`.
+        unsafe { Error::construct(error, vtable, None, None) }
+    }
+
+    #[track_caller]
+    pub(crate) fn from_adhoc<M>(message: M) -> Self
+    where
+        M: Display + Debug + Send + Sync + 'static,
+    {
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<MessageError<M>>,
+            object_ref: object_ref::<MessageError<M>>,
+            object_mut: object_mut::<MessageError<M>>,
+            object_downcast: object_downcast::<M>,
+            object_downcast_mut: object_downcast_mut::<M>,
+            object_drop_rest: object_drop_front::<M>,
+            object_inner: no_inner,
+            context_type: None,
+            error_type: Some(type_name::<M>),
+            context_reports: None,
+        };
+
+        let error = MessageError(message);
+        let handler = hook::capture_handler(&error);
+
+        // SAFETY: MessageError is repr(transparent), so it is fine for the
+        // downcast and drop_rest entries to treat the object as an `M`.
+        let error = unsafe { Error::construct(error, vtable, Some(Backtrace::capture()), handler) };
+        scope::apply(error)
+    }
+
+    #[track_caller]
+    pub(crate) fn from_display<M>(message: M) -> Self
+    where
+        M: Display + Send + Sync + 'static,
+    {
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<DisplayError<M>>,
+            object_ref: object_ref::<DisplayError<M>>,
+            object_mut: object_mut::<DisplayError<M>>,
+            object_downcast: object_downcast::<M>,
+            object_downcast_mut: object_downcast_mut::<M>,
+            object_drop_rest: object_drop_front::<M>,
+            object_inner: no_inner,
+            context_type: None,
+            error_type: Some(type_name::<M>),
+            context_reports: None,
+        };
+
+        let error = DisplayError(message);
+        let handler = hook::capture_handler(&error);
+
+        // SAFETY: DisplayError is repr(transparent).
+        let error = unsafe { Error::construct(error, vtable, Some(Backtrace::capture()), handler) };
+        scope::apply(error)
+    }
+
+    #[track_caller]
+    pub(crate) fn from_boxed(error: Box<dyn StdError + Send + Sync>) -> Self {
+        let error = match error.downcast::<Compat>() {
+            Ok(compat) => return compat.into_inner(),
+            Err(error) => error,
+        };
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<BoxedError>,
+            object_ref: object_ref::<BoxedError>,
+            object_mut: object_mut::<BoxedError>,
+            object_downcast: object_downcast::<Box<dyn StdError + Send + Sync>>,
+            object_downcast_mut: object_downcast_mut::<Box<dyn StdError + Send + Sync>>,
+            object_drop_rest: object_drop_front::<Box<dyn StdError + Send + Sync>>,
+            object_inner: no_inner,
+            context_type: None,
+            error_type: Some(type_name::<Box<dyn StdError + Send + Sync>>),
+            context_reports: None,
+        };
+
+        let backtrace = backtrace_if_absent(&*error);
+        let error = BoxedError(error);
+        let handler = hook::capture_handler(&error);
+
+        // SAFETY: BoxedError is repr(transparent).
+        let error = unsafe { Error::construct(error, vtable, backtrace, handler) };
+        scope::apply(error)
+    }
+
+    // A context layer and the error it wraps, held in a single allocation.
+    #[track_caller]
+    pub(crate) fn from_context<C, E>(context: C, error: E) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+        E: StdError + Send + Sync + 'static,
+    {
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<ContextError<C, E>>,
+            object_ref: object_ref::<ContextError<C, E>>,
+            object_mut: object_mut::<ContextError<C, E>>,
+            object_downcast: context_downcast::<C, E>,
+            object_downcast_mut: context_downcast_mut::<C, E>,
+            object_drop_rest: context_drop_rest::<C, E>,
+            object_inner: no_inner,
+            context_type: Some(type_name::<C>),
+            error_type: Some(type_name::<E>),
+            context_reports: None,
+        };
+
+        let backtrace = backtrace_if_absent(&error);
+        let error = ContextError { context, error };
+        let handler = hook::capture_handler(&error);
+
+        // SAFETY: the vtable was built for `ContextError<C, E>`.
+        let error = unsafe { Error::construct(error, vtable, backtrace, handler) };
+        scope::apply(error)
+    }
+
+    // SAFETY: every entry of `vtable` must be valid for an `ErrorImpl<E>`.
+    #[track_caller]
+    unsafe fn construct<E>(
+        error: E,
+        vtable: &'static ErrorVTable,
+        backtrace: Option<Backtrace>,
+        handler: Option<Box<dyn ReportHandler>>,
+    ) -> Self
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        let inner = Box::new(ErrorImpl {
+            vtable,
+            location: Location::caller(),
+            backtrace,
+            handler,
+            exit_code: None,
+            scoped: false,
+            sections: Vec::new(),
+            attachments: Vec::new(),
+            _object: error,
+        });
+        Error {
+            inner: Own::new(inner).cast::<ErrorImpl>(),
+        }
+    }
+
+    #[track_caller]
+    pub fn context<C>(self, context: C) -> Self
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<ContextError<C, Error>>,
+            object_ref: object_ref::<ContextError<C, Error>>,
+            object_mut: object_mut::<ContextError<C, Error>>,
+            object_downcast: context_chain_downcast::<C>,
+            object_downcast_mut: context_chain_downcast_mut::<C>,
+            object_drop_rest: context_chain_drop_rest::<C>,
+            object_inner: context_chain_inner::<C>,
+            context_type: Some(type_name::<C>),
+            error_type: None,
+            context_reports: None,
+        };
+
+        let error = ContextError {
+            context,
+            error: self,
+        };
+
+        // SAFETY: the vtable was built for `ContextError<C, Error>`. The inner
+        // error already has a backtrace and handler.
+        unsafe { Error::construct(error, vtable, None, None) }
+    }
+
+    // Like `context`, with the context value also rendering nested reports of
+    // its own after the causes.
+    #[track_caller]
+    pub(crate) fn context_with_reports<C>(self, context: C) -> Self
+    where
+        C: NestedReports + Display + Send + Sync + 'static,
+    {
+        let vtable = &ErrorVTable {
+            object_drop: object_drop::<ContextError<C, Error>>,
+            object_ref: object_ref::<ContextError<C, Error>>,
+            object_mut: object_mut::<ContextError<C, Error>>,
+            object_downcast: context_chain_downcast::<C>,
+            object_downcast_mut: context_chain_downcast_mut::<C>,
+            object_drop_rest: context_chain_drop_rest::<C>,
+            object_inner: context_chain_inner::<C>,
+            context_type: Some(type_name::<C>),
+            error_type: None,
+            context_reports: Some(context_chain_reports::<C>),
+        };
+
+        let error = ContextError {
+            context,
+            error: self,
+        };
+
+        // SAFETY: the vtable was built for `ContextError<C, Error>`. The inner
+        // error already has a backtrace and handler.
+        unsafe { Error::construct(error, vtable, None, None) }
+    }
+
+    #[track_caller]
+    pub fn extend_context<C>(&mut self, context: C)
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        // SAFETY: `self` is read out and written back with nothing in between
+        // that can unwind, so it is never observed twice or dropped twice.
+        unsafe { ptr::write(self, ptr::read(self).context(context)) }
+    }
+
+    /// The backtrace captured when this error was first created.
+    ///
+    /// Adding context to an existing `Error` keeps the original backtrace rather
+    /// than capturing a new one.
+    pub fn backtrace(&self) -> &Backtrace {
+        // SAFETY: `inner` is a live ErrorImpl for as long as `self` is borrowed.
+        unsafe { ErrorImpl::backtrace(self.inner.by_ref()) }
+    }
+
+    /// Attach the process exit code to use if this error ends up being
+    /// returned from `main` through a [`Report`](crate::Report). An error never
+    /// exits successfully, so `Report` treats 0 as 1.
+    pub fn with_exit_code(mut self, code: u8) -> Self {
+        // SAFETY: `inner` is uniquely borrowed through `self`, and only the
+        // header is touched.
+        unsafe { self.inner.by_mut().deref_mut().exit_code = Some(code) };
+        self
+    }
+
+    /// The exit code attached to the outermost layer that has one.
+    pub fn exit_code(&self) -> Option<u8> {
+        // SAFETY: `inner` is a live ErrorImpl for as long as `self` is borrowed.
+        unsafe { ErrorImpl::exit_code(self.inner.by_ref()) }
+    }
+
+    pub(crate) fn with_section(
+        mut self,
+        heading: Heading,
+        body: Box<dyn Display + Send + Sync>,
+    ) -> Self {
+        // SAFETY: `inner` is uniquely borrowed through `self`, and only the
+        // header is touched.
+        let sections = unsafe { &mut self.inner.by_mut().deref_mut().sections };
+        sections.push(ReportSection { heading, body });
+        self
+    }
+
+    pub(crate) fn with_location(mut self, location: &'static Location<'static>) -> Self {
+        // SAFETY: `inner` is uniquely borrowed through `self`, and only the
+        // header is touched.
+        unsafe { self.inner.by_mut().deref_mut().location = location };
+        self
+    }
+
+    pub(crate) fn mark_scoped(mut self) -> Self {
+        // SAFETY: `inner` is uniquely borrowed through `self`, and only the
+        // header is touched.
+        unsafe { self.inner.by_mut().deref_mut().scoped = true };
+        self
+    }
+
+    // Whether this error, an `Error` it holds through `Compat` or one of the
+    // children of a `MultiError` in its chain already went through the scopes.
+    pub(crate) fn is_scoped(&self) -> bool {
+        self.layers().iter().any(|layer| layer.scoped)
+            || self
+                .multi()
+                .is_some_and(|multi| multi.iter().any(Error::is_scoped))
+    }
+
+    /// Where the outermost layer of this error was created: the `?`, `context`
+    /// call or macro invocation that produced it.
+    pub fn location(&self) -> &'static Location<'static> {
+        // SAFETY: `inner` is a live ErrorImpl for as long as `self` is borrowed.
+        unsafe { self.inner.by_ref().deref().location }
+    }
+
+    // What is known about each entry of `chain()`: whether it is a context
+    // value, the wrapped error or one of its sources, its type and where it
+    // was created. The sources of a foreign error are opaque.
+    pub(crate) fn links(&self) -> Vec<Link<'_>> {
+        let mut links = Vec::new();
+        let mut layer = Some(self);
+        for (index, cause) in self.chain().enumerate() {
+            if index < links.len() {
+                continue;
+            }
+            let error = match layer.take() {
+                Some(error) => error,
+                None => match cause.downcast_ref::<Compat>() {
+                    Some(compat) => compat.as_ref(),
+                    None => {
+                        links.push(Link {
+                            kind: LinkKind::Source,
+                            type_name: None,
+                            location: None,
+                        });
+                        continue;
+                    }
+                },
+            };
+
+            // SAFETY: `inner` is a live ErrorImpl for as long as `error` is
+            // borrowed.
+            let vtable = unsafe { vtable(error.inner.ptr) };
+            let mut location = Some(error.location());
+            if let Some(context_type) = vtable.context_type {
+                links.push(Link {
+                    kind: LinkKind::Context,
+                    type_name: Some(context_type()),
+                    location: location.take().map(SourceLocation::from),
+                });
+            }
+            if let Some(error_type) = vtable.error_type {
+                links.push(Link {
+                    kind: LinkKind::Error,
+                    type_name: Some(error_type()),
+                    location: location.map(SourceLocation::from),
+                });
+            }
+            // SAFETY: as above.
+            layer = unsafe { (vtable.object_inner)(error.inner.by_ref()) };
+        }
+
+        // An error reconstructed from another process knows what each of its
+        // entries was over there.
+        for (link, cause) in links.iter_mut().zip(self.chain()) {
+            if let Some(remote) = cause.downcast_ref::<RemoteError>() {
+                *link = remote.link();
+            }
+        }
+        links
+    }
+
+    pub(crate) fn with_attachment(mut self, attachment: Attachment) -> Self {
+        // SAFETY: `inner` is uniquely borrowed through `self`, and only the
+        // header is touched.
+        let attachments = unsafe { &mut self.inner.by_mut().deref_mut().attachments };
+        attachments.push(attachment);
+        self
+    }
+
+    // The header of every layer, innermost first, so that what was added to
+    // them comes out in the order it was added.
+    pub(crate) fn layers(&self) -> Vec<&ErrorImpl> {
+        let mut layers = Vec::new();
+        // SAFETY: `inner` is a live ErrorImpl for as long as `self` is borrowed.
+        unsafe { ErrorImpl::layers(self.inner.by_ref(), &mut layers) };
+        layers
+    }
+
+    pub(crate) fn sections(&self) -> impl Iterator<Item = &ReportSection> {
+        self.layers().into_iter().flat_map(|layer| &layer.sections)
+    }
+
+    pub(crate) fn attachments(&self) -> impl Iterator<Item = &Attachment> {
+        self.layers()
+            .into_iter()
+            .flat_map(|layer| &layer.attachments)
+    }
+
+    // The context values of every layer that render nested reports, innermost
+    // first.
+    pub(crate) fn context_reports(&self) -> impl Iterator<Item = &dyn NestedReports> {
+        self.layers().into_iter().filter_map(|layer| {
+            let reports = layer.vtable.context_reports?;
+            // SAFETY: the entry was set for this layer's own type.
+            Some(unsafe { reports(Ref::new(layer)) })
+        })
+    }
+
+    /// The handler created for this error by the hook installed with
+    /// [`set_hook`](crate::set_hook), if any.
+    pub fn handler(&self) -> Option<&dyn ReportHandler> {
+        // SAFETY: `inner` is a live ErrorImpl for as long as `self` is borrowed.
+        unsafe { ErrorImpl::handler(self.inner.by_ref()) }
+    }
+
+    /// Look for a `T` among the context values and the wrapped error of this
+    /// error's own layers, outermost first. These are exactly the values
+    /// [`downcast`](Self::downcast) can take out, so whenever this finds a `T`,
+    /// [`is`](Self::is) returns true and `downcast::<T>()` succeeds.
+    ///
+    /// `T` only has to be `Display`, so the plain sources of a foreign error
+    /// cannot be checked here; use [`find_source`](Self::find_source) to find
+    /// an error type anywhere in the chain, and
+    /// [`find_in_children`](Self::find_in_children) to look inside a
+    /// [`MultiError`](crate::MultiError).
+    pub fn downcast_ref<T>(&self) -> Option<&T>
+    where
+        T: Display + Send + Sync + 'static,
+    {
+        let target = TypeId::of::<T>();
+        // SAFETY: a pointer returned for `target` points at a `T`.
+        unsafe {
+            let addr = (vtable(self.inner.ptr).object_downcast)(self.inner.by_ref(), target)?;
+            Some(addr.cast::<T>().deref())
+        }
+    }
+
+    /// Like [`downcast_ref`](Self::downcast_ref), but also checks every entry of
+    /// [`chain()`](Self::chain), including the sources of a foreign error. This
+    /// finds an `io::Error` wrapped inside another error type after any number
+    /// of `context` calls.
+    pub fn find_source<T>(&self) -> Option<&T>
+    where
+        T: StdError + Send + Sync + 'static,
+    {
+        self.downcast_ref::<T>()
+            .or_else(|| self.chain().find_map(|cause| cause.downcast_ref::<T>()))
+    }
+
+    /// Look for a `T` in the children of the first
+    /// [`MultiError`](crate::MultiError) in the chain, including one rebuilt
+    /// by [`from_json`](Self::from_json), as with
+    /// [`MultiError::find`](crate::MultiError::find).
+    pub fn find_in_children<T>(&self) -> Option<&T>
+    where
+        T: Display + Send + Sync + 'static,
+    {
+        self.multi()?.find::<T>()
+    }
+
+    /// Like [`downcast_ref`](Self::downcast_ref), but only the context values and
+    /// the wrapped error can be borrowed mutably.
+    pub fn downcast_mut<T>(&mut self) -> Option<&mut T>
+    where
+        T: Display + Send + Sync + 'static,
+    {
+        let target = TypeId::of::<T>();
+        // SAFETY: a pointer returned for `target` points at a `T`.
+        unsafe {
+            let addr = (vtable(self.inner.ptr).object_downcast_mut)(self.inner.by_mut(), target)?;
+            Some(addr.cast::<T>().deref_mut())
+        }
+    }
+
+    /// Take the `T` out of this error. Any context layers outside of it are
+    /// dropped; on failure the error is handed back unchanged.
+    pub fn downcast<T>(self) -> std::result::Result<T, Self>
+    where
+        T: Display + Send + Sync + 'static,
+    {
+        let target = TypeId::of::<T>();
+        let inner = self.inner;
+        // SAFETY: the `T` is moved out before drop_rest frees everything but
+        // it, and `self` is forgotten so nothing is dropped twice.
+        unsafe {
+            let addr = match (vtable(inner.ptr).object_downcast)(inner.by_ref(), target) {
+                Some(addr) => addr.cast::<T>().ptr,
+                None => return Err(self),
+            };
+            let outer = ManuallyDrop::new(self);
+            let error = addr.as_ptr().read();
+            (vtable(outer.inner.ptr).object_drop_rest)(outer.inner, target);
+            Ok(error)
+        }
+    }
+
+    /// Convert into a boxed std error without losing anything: `source()` still
+    /// walks every context layer, and the `Debug` output is the full report
+    /// including the backtrace.
+    pub fn into_boxed_dyn_error(self) -> Box<dyn StdError + Send + Sync + 'static> {
+        Box::new(self.into_std())
+    }
+}
+
+impl<E> From<E> for Error
+where
+    E: StdError + Send + Sync + 'static,
+{
+    #[track_caller]
+    fn from(value: E) -> Self {
+        // A `Compat` is an `Error` already; unwrap it instead of boxing it again.
+        let mut slot = Some(value);
+        if let Some(compat) = (&mut slot as &mut dyn Any).downcast_mut::<Option<Compat>>() {
+            if let Some(compat) = compat.take() {
+                return compat.into_inner();
+            }
+        }
+        match slot {
+            Some(value) => Error::from_std(value),
+            None => unreachable!(),
+        }
+    }
+}
+
+impl Deref for Error {
+    type Target = dyn StdError + Send + Sync + 'static;
+
+    fn deref(&self) -> &Self::Target {
+        // SAFETY: `inner` is a live ErrorImpl for as long as `self` is borrowed.
+        unsafe { ErrorImpl::error(self.inner.by_ref()) }
+    }
+}
+
+impl DerefMut for Error {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        // SAFETY: `inner` is uniquely borrowed through `self`.
+        unsafe { ErrorImpl::error_mut(self.inner.by_mut()) }
+    }
+}
+
+impl Drop for Error {
+    fn drop(&mut self) {
+        // SAFETY: `inner` is not used again after being dropped.
+        unsafe { (vtable(self.inner.ptr).object_drop)(self.inner) }
+    }
+}
+
+impl ErrorImpl {
+    pub(crate) unsafe fn error(this: Ref<'_, Self>) -> &(dyn StdError + Send + Sync + 'static) {
+        (vtable(this.ptr).object_ref)(this)
+    }
+
+    pub(crate) unsafe fn error_mut(
+        this: Mut<'_, Self>,
+    ) -> &mut (dyn StdError + Send + Sync + 'static) {
+        (vtable(this.ptr).object_mut)(this)
+    }
+
+    pub(crate) unsafe fn handler(this: Ref<'_, Self>) -> Option<&dyn ReportHandler> {
+        if let Some(handler) = &this.deref().handler {
+            return Some(handler.as_ref());
+        }
+        (vtable(this.ptr).object_inner)(this)?.handler()
+    }
+
+    pub(crate) unsafe fn exit_code(this: Ref<'_, Self>) -> Option<u8> {
+        if let Some(code) = this.deref().exit_code {
+            return Some(code);
+        }
+        (vtable(this.ptr).object_inner)(this)?.exit_code()
+    }
+
+    pub(crate) unsafe fn layers<'a>(this: Ref<'a, Self>, out: &mut Vec<&'a ErrorImpl>) {
+        match (vtable(this.ptr).object_inner)(this) {
+            Some(inner) => ErrorImpl::layers(inner.inner.by_ref(), out),
+            None => {
+                // An `Error` that went through `Compat` into a foreign error
+                // still has its layers.
+                let mut next = ErrorImpl::error(this).source();
+                while let Some(cause) = next {
+                    if let Some(compat) = cause.downcast_ref::<Compat>() {
+                        ErrorImpl::layers(compat.as_ref().inner.by_ref(), out);
+                        break;
+                    }
+                    next = cause.source();
+                }
+            }
+        }
+        out.push(this.deref());
+    }
+
+    pub(crate) unsafe fn backtrace(this: Ref<'_, Self>) -> &Backtrace {
+        if let Some(backtrace) = &this.deref().backtrace {
+            return backtrace;
+        }
+        if let Some(inner) = (vtable(this.ptr).object_inner)(this) {
+            return inner.backtrace();
+        }
+        provided_backtrace(ErrorImpl::error(this)).unwrap_or(&DISABLED)
+    }
+}
+
+static DISABLED: Backtrace = Backtrace::disabled();
+
+// Stable std has no way to ask an arbitrary error for its backtrace, but one
+// of our own errors somewhere in the source chain already carries one.
+fn provided_backtrace<'a>(error: &'a (dyn StdError + 'static)) -> Option<&'a Backtrace> {
+    let mut next = Some(error);
+    while let Some(cause) = next {
+        if let Some(compat) = cause.downcast_ref::<Compat>() {
+            let backtrace = compat.as_ref().backtrace();
+            if backtrace.status() == BacktraceStatus::Captured {
+                return Some(backtrace);
+            }
+        }
+        next = cause.source();
+    }
+    None
+}
+
+// `Backtrace::capture` already honours RUST_LIB_BACKTRACE and RUST_BACKTRACE,
+// and is cheap when both are unset.
+fn backtrace_if_absent(error: &(dyn StdError + 'static)) -> Option<Backtrace> {
+    match provided_backtrace(error) {
+        Some(_) => None,
+        None => Some(Backtrace::capture()),
+    }
+}
+
+pub(crate) struct Link<'a> {
+    pub(crate) kind: LinkKind,
+    pub(crate) type_name: Option<&'a str>,
+    pub(crate) location: Option<SourceLocation<'a>>,
+}
+
+// A `Location`, or one parsed from a backtrace or a serialized report, where
+// the line and column may be missing.
+#[derive(Clone, Copy)]
+pub(crate) struct SourceLocation<'a> {
+    pub(crate) file: &'a str,
+    pub(crate) line: Option<u32>,
+    pub(crate) column: Option<u32>,
+}
+
+impl<'a> SourceLocation<'a> {
+    // Splits std's "file:line:column".
+    pub(crate) fn parse(location: &'a str) -> Self {
+        let mut parts = location.rsplitn(3, ':');
+        let column = parts.next().and_then(|column| column.parse().ok());
+        let line = parts.next().and_then(|line| line.parse().ok());
+        match (parts.next(), line, column) {
+            (Some(file), Some(line), Some(column)) => SourceLocation {
+                file,
+                line: Some(line),
+                column: Some(column),
+            },
+            _ => SourceLocation {
+                file: location,
+                line: None,
+                column: None,
+            },
+        }
+    }
+}
+
+impl From<&'static Location<'static>> for SourceLocation<'static> {
+    fn from(location: &'static Location<'static>) -> Self {
+        SourceLocation {
+            file: location.file(),
+            line: Some(location.line()),
+            column: Some(location.column()),
+        }
+    }
+}
+
+impl Display for SourceLocation<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.file)?;
+        if let Some(line) = self.line {
+            write!(f, ":{}", line)?;
+            if let Some(column) = self.column {
+                write!(f, ":{}", column)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LinkKind {
+    Context,
+    Error,
+    Source,
+}
+
+unsafe fn vtable(p: NonNull<ErrorImpl>) -> &'static ErrorVTable {
+    (*p.as_ptr()).vtable
+}
+
+unsafe fn object_drop<E>(e: Own<ErrorImpl>) {
+    drop(e.cast::<ErrorImpl<E>>().boxed());
+}
+
+// Drops everything except the `E`, which the caller has already moved out.
+unsafe fn object_drop_front<E>(e: Own<ErrorImpl>, target: TypeId) {
+    let _ = target;
+    drop(e.cast::<ErrorImpl<ManuallyDrop<E>>>().boxed());
+}
+
+unsafe fn object_ref<E>(e: Ref<'_, ErrorImpl>) -> &(dyn StdError + Send + Sync + 'static)
+where
+    E: StdError + Send + Sync + 'static,
+{
+    &e.cast::<ErrorImpl<E>>().deref()._object
+}
+
+unsafe fn object_mut<E>(e: Mut<'_, ErrorImpl>) -> &mut (dyn StdError + Send + Sync + 'static)
+where
+    E: StdError + Send + Sync + 'static,
+{
+    &mut e.cast::<ErrorImpl<E>>().deref_mut()._object
+}
+
+unsafe fn object_downcast<E>(e: Ref<'_, ErrorImpl>, target: TypeId) -> Option<Ref<'_, ()>>
+where
+    E: 'static,
+{
+    if TypeId::of::<E>() == target {
+        let unerased = e.cast::<ErrorImpl<E>>().deref();
+        Some(Ref::new(&unerased._object).cast::<()>())
+    } else {
+        None
+    }
+}
+
+unsafe fn object_downcast_mut<E>(e: Mut<'_, ErrorImpl>, target: TypeId) -> Option<Mut<'_, ()>>
+where
+    E: 'static,
+{
+    if TypeId::of::<E>() == target {
+        let unerased = e.cast::<ErrorImpl<E>>().deref_mut();
+        Some(Mut::new(&mut unerased._object).cast::<()>())
+    } else {
+        None
+    }
+}
+
+unsafe fn no_inner(e: Ref<'_, ErrorImpl>) -> Option<&Error> {
+    let _ = e;
+    None
+}
+
+unsafe fn context_downcast<C, E>(e: Ref<'_, ErrorImpl>, target: TypeId) -> Option<Ref<'_, ()>>
+where
+    C: 'static,
+    E: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextError<C, E>>>().deref();
+    if TypeId::of::<C>() == target {
+        Some(Ref::new(&unerased._object.context).cast::<()>())
+    } else if TypeId::of::<E>() == target {
+        Some(Ref::new(&unerased._object.error).cast::<()>())
+    } else {
+        None
+    }
+}
+
+unsafe fn context_downcast_mut<C, E>(e: Mut<'_, ErrorImpl>, target: TypeId) -> Option<Mut<'_, ()>>
+where
+    C: 'static,
+    E: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextError<C, E>>>().deref_mut();
+    if TypeId::of::<C>() == target {
+        Some(Mut::new(&mut unerased._object.context).cast::<()>())
+    } else if TypeId::of::<E>() == target {
+        Some(Mut::new(&mut unerased._object.error).cast::<()>())
+    } else {
+        None
+    }
+}
+
+unsafe fn context_drop_rest<C, E>(e: Own<ErrorImpl>, target: TypeId)
+where
+    C: 'static,
+    E: 'static,
+{
+    if TypeId::of::<C>() == target {
+        drop(
+            e.cast::<ErrorImpl<ContextError<ManuallyDrop<C>, E>>>()
+                .boxed(),
+        );
+    } else {
+        drop(
+            e.cast::<ErrorImpl<ContextError<C, ManuallyDrop<E>>>>()
+                .boxed(),
+        );
+    }
+}
+
+unsafe fn context_chain_downcast<C>(e: Ref<'_, ErrorImpl>, target: TypeId) -> Option<Ref<'_, ()>>
+where
+    C: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().deref();
+    if TypeId::of::<C>() == target {
+        Some(Ref::new(&unerased._object.context).cast::<()>())
+    } else {
+        let source = &unerased._object.error;
+        (vtable(source.inner.ptr).object_downcast)(source.inner.by_ref(), target)
+    }
+}
+
+unsafe fn context_chain_downcast_mut<C>(
+    e: Mut<'_, ErrorImpl>,
+    target: TypeId,
+) -> Option<Mut<'_, ()>>
+where
+    C: 'static,
+{
+    let unerased = e.cast::<ErrorImpl<ContextError<C, Error>>>().deref_mut();
+    if TypeId::of::<C>() == target {
+        Some(Mut::new(&mut unerased._object.context).cast::<()>())
+    } else {
+        let source = &mut unerased._object.error;
+        (vtable(source.inner.ptr).object_downcast_mut)(source.inner.by_mut(), target)
+    }
+}
+
+unsafe fn context_chain_drop_rest<C>(e: Own<ErrorImpl>, target: TypeId)
+where
+    C: 'static,
+{
+    if TypeId::of::<C>() == target {
+        drop(
+            e.cast::<ErrorImpl<ContextError<ManuallyDrop<C>, Error>>>()
+                .boxed(),
+        );
+    } else {
+        let unerased = e
+            .cast::<ErrorImpl<ContextError<C, ManuallyDrop<Error>>>>()
+            .boxed();
+        let inner = ptr::read(&unerased._object.error);
+        drop(unerased);
+        (vtable(inner.inner.ptr).object_drop_rest)(inner.inner, target);
+    }
+}
+
+unsafe fn context_chain_inner<C>(e: Ref<'_, ErrorImpl>) -> Option<&Error>
+where
+    C: 'static,
+{
+    Some(
+        &e.cast::<ErrorImpl<ContextError<C, Error>>>()
+            .deref()
+            ._object
+            .error,
+    )
+}
+
+unsafe fn context_chain_reports<C>(e: Ref<'_, ErrorImpl>) -> &dyn NestedReports
+where
+    C: NestedReports + 'static,
+{
+    &e.cast::<ErrorImpl<ContextError<C, Error>>>()
+        .deref()
+        ._object
+        .context
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        error::Error as StdError,
+        fmt, io, mem,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+    };
+
+    use crate::{Context, Error, MultiError};
+
+    // Counts how many times it has been dropped.
+    #[derive(Debug)]
+    struct Counted(Arc<AtomicUsize>);
+
+    impl Counted {
+        fn new() -> (Self, Arc<AtomicUsize>) {
+            let drops = Arc::new(AtomicUsize::new(0));
+            (Counted(Arc::clone(&drops)), drops)
+        }
+    }
+
+    impl Drop for Counted {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    impl fmt::Display for Counted {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("counted")
+        }
+    }
+
+    impl StdError for Counted {}
+
+    #[derive(Debug)]
+    struct Outer {
+        source: io::Error,
+    }
+
+    impl fmt::Display for Outer {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("outer")
+        }
+    }
+
+    impl StdError for Outer {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(&self.source)
+        }
+    }
+
+    #[test]
+    fn find_source_through_context_and_foreign_sources() {
+        let error = Err::<(), _>(Outer {
+            source: io::Error::from(io::ErrorKind::NotFound),
+        })
+        .context("a")
+        .context("b")
+        .unwrap_err();
+
+        let found = error
+            .find_source::<io::Error>()
+            .expect("io::Error in the chain");
+        assert_eq!(found.kind(), io::ErrorKind::NotFound);
+        assert!(error.is_source::<io::Error>());
+        assert!(error.is_source::<Outer>());
+        assert!(!error.is_source::<fmt::Error>());
+    }
+
+    #[test]
+    fn multi_error_children_are_searched_explicitly() {
+        let mut nested = MultiError::new();
+        nested.push(Error::msg("second").context(5u16));
+        let mut multi = MultiError::new();
+        multi.push(Error::msg("first").context(3u8));
+        multi.push(Outer {
+            source: io::Error::from(io::ErrorKind::NotFound),
+        });
+        multi.push(nested);
+        let error = Error::from(multi).context("batch");
+
+        assert_eq!(error.downcast_ref::<&str>(), Some(&"batch"));
+        assert!(error.is::<MultiError>());
+        assert!(!error.is::<u8>());
+        assert!(!error.is::<Outer>());
+        assert!(error.find_source::<io::Error>().is_none());
+
+        assert_eq!(error.find_in_children::<u8>(), Some(&3));
+        assert_eq!(error.find_in_children::<u16>(), Some(&5));
+        assert!(error.find_in_children::<Outer>().is_some());
+        assert!(error.find_in_children::<String>().is_none());
+        assert!(Error::msg("alone").find_in_children::<&str>().is_none());
+    }
+
+    #[test]
+    fn is_means_downcast_succeeds() {
+        let mut multi = MultiError::new();
+        multi.push(Error::msg("child").context(3u8));
+        let foreign = Outer {
+            source: io::Error::other("inner"),
+        };
+        let errors = [
+            Error::from(multi).context("batch"),
+            Error::from(foreign).context(7u32),
+        ];
+        for error in errors {
+            let is_u8 = error.is::<u8>();
+            let is_io = error.is::<io::Error>();
+            let is_u32 = error.is::<u32>();
+            assert!(!is_u8 && !is_io);
+            assert_eq!(error.downcast::<u32>().is_ok(), is_u32);
+        }
+    }
+
+    #[test]
+    fn downcast_ref_finds_context_values_and_wrapped_error() {
+        let error: Error = Err::<(), _>(io::Error::other("inner"))
+            .context(7u32)
+            .context("outer")
+            .unwrap_err();
+
+        assert_eq!(error.downcast_ref::<u32>(), Some(&7));
+        assert_eq!(error.downcast_ref::<&str>(), Some(&"outer"));
+        assert!(error.is::<io::Error>());
+        assert!(!error.is::<String>());
+    }
+
+    #[test]
+    fn error_is_one_pointer() {
+        assert_eq!(mem::size_of::<Error>(), mem::size_of::<usize>());
+        assert_eq!(mem::size_of::<Option<Error>>(), mem::size_of::<usize>());
+        assert_eq!(mem::size_of::<Result<(), Error>>(), mem::size_of::<usize>());
+    }
+
+    #[test]
+    fn every_constructor_drops_its_value_once() {
+        let (value, drops) = Counted::new();
+        drop(Error::from(value));
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+        let (value, drops) = Counted::new();
+        drop(Error::msg(value));
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+        let (value, drops) = Counted::new();
+        drop(None::<()>.context(value).unwrap_err());
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+        let (value, drops) = Counted::new();
+        let boxed: Box<dyn StdError + Send + Sync> = Box::new(value);
+        drop(crate::regardless!(boxed));
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+        let (context, context_drops) = Counted::new();
+        let (value, drops) = Counted::new();
+        drop(Err::<(), _>(value).context(context).unwrap_err());
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        assert_eq!(context_drops.load(Ordering::SeqCst), 1);
+
+        let (context, context_drops) = Counted::new();
+        let (value, drops) = Counted::new();
+        drop(Error::from(value).context(context));
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        assert_eq!(context_drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn downcast_by_value_through_nested_context() {
+        let (value, drops) = Counted::new();
+        let (context, context_drops) = Counted::new();
+        let error = Error::from(value).context(context).context("outer");
+
+        // The outermost `Counted` is the context value; everything else,
+        // including the wrapped `Counted`, is dropped.
+        let context = error.downcast::<Counted>().expect("a Counted in the chain");
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        assert_eq!(context_drops.load(Ordering::SeqCst), 0);
+        drop(context);
+        assert_eq!(context_drops.load(Ordering::SeqCst), 1);
+
+        let (value, drops) = Counted::new();
+        let error = Error::from(value).context(5u32).context("outer");
+        assert_eq!(error.downcast::<u32>().ok(), Some(5));
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+        let (value, drops) = Counted::new();
+        let error = Err::<(), _>(value)
+            .context("inner")
+            .unwrap_err()
+            .context(6u8);
+        let inner = error.downcast::<&str>().ok();
+        assert_eq!(inner, Some("inner"));
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+
+        let (value, drops) = Counted::new();
+        let error = Error::from(value).context("outer");
+        let error = error
+            .downcast::<String>()
+            .expect_err("no String in the chain");
+        assert_eq!(error.to_string(), "outer");
+        drop(error);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn downcast_mut_changes_context_and_error() {
+        let mut error = Error::msg(String::from("message"))
+            .context(1u32)
+            .context("outer");
+        *error.downcast_mut::<u32>().unwrap() += 1;
+        error.downcast_mut::<String>().unwrap().push('!');
+        assert_eq!(error.downcast_ref::<u32>(), Some(&2));
+        assert_eq!(format!("{:#}", error), "outer: 2: message!");
+        assert!(error.downcast_mut::<u64>().is_none());
+    }
+
+    #[test]
+    fn extend_context_wraps_in_place() {
+        let (value, drops) = Counted::new();
+        let mut error = Error::from(value);
+        let location = error.location();
+        error.extend_context("first");
+        error.extend_context(String::from("second"));
+        assert_eq!(format!("{:#}", error), "second: first: counted");
+        assert_eq!(error.chain().count(), 3);
+        assert_ne!(error.location(), location);
+        drop(error);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+}
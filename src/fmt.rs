@@ -0,0 +1,408 @@
+use std::{
+    backtrace::BacktraceStatus,
+    fmt::{self, Display, Write},
+};
+
+use crate::{section::Heading, Error};
+
+impl Error {
+    pub(crate) fn display(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", &**self)?;
+
+        if f.alternate() {
+            for cause in self.chain().skip(1) {
+                write!(f, ": {}", cause)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    pub(crate) fn debug(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.handler() {
+            Some(handler) => handler.debug(self, f),
+            None => self.default_debug(f),
+        }
+    }
+
+    pub(crate) fn default_debug(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt::Debug::fmt(&**self, f);
+        }
+
+        self.report(f, &Theme::PLAIN)
+    }
+
+    pub(crate) fn report(&self, f: &mut fmt::Formatter<'_>, theme: &Theme) -> fmt::Result {
+        self.write_report(f, theme, false)
+    }
+
+    // A nested report is one of the errors of a `MultiError` or an earlier
+    // attempt of `retry`, and leaves out the backtrace.
+    fn write_report(&self, f: &mut dyn Write, theme: &Theme, nested: bool) -> fmt::Result {
+        let error = &**self;
+
+        let links = self.links();
+
+        theme.headline.paint(f, error)?;
+        if let Some(location) = links[0].location {
+            f.write_str("\n    ")?;
+            theme.location.paint(f, format_args!("at {}", location))?;
+        }
+
+        if let Some(cause) = error.source() {
+            write!(f, "\n\n")?;
+            theme.heading.paint(f, "Caused by:")?;
+            let multiple = cause.source().is_some();
+            for (n, error) in self.chain().skip(1).enumerate() {
+                writeln!(f)?;
+                let mut indented = Indented {
+                    inner: f,
+                    number: if multiple { Some(n) } else { None },
+                    style: theme.number,
+                    started: false,
+                    line_start: false,
+                };
+                theme.cause.paint(&mut indented, error)?;
+                if let Some(location) = links[n + 1].location {
+                    indented.write_str("\n")?;
+                    theme
+                        .location
+                        .paint(&mut indented, format_args!("at {}", location))?;
+                }
+            }
+        }
+
+        // Only this error's own values are asked, so the reports of a nested
+        // error are rendered once, inside its own report.
+        let multi = self.multi().map(|multi| multi as &dyn NestedReports);
+        for nested in multi.into_iter().chain(self.context_reports()) {
+            let reports = nested.reports();
+            let hidden = nested.hidden();
+            if reports.is_empty() && hidden == 0 {
+                continue;
+            }
+            write_sub_reports(f, theme, nested.heading(), reports.into_iter())?;
+            if hidden > 0 {
+                write!(f, "\n    and {} more", hidden)?;
+            }
+        }
+
+        let mut attachments = self.attachments().filter(|a| a.is_printable()).peekable();
+        if attachments.peek().is_some() {
+            write!(f, "\n\n")?;
+            theme.heading.paint(f, "Attachments:")?;
+            for attachment in attachments {
+                f.write_str("\n    ")?;
+                write!(
+                    Hanging {
+                        inner: f,
+                        indent: 4
+                    },
+                    "{}",
+                    attachment
+                )?;
+            }
+        }
+
+        for section in self.sections() {
+            write!(f, "\n\n")?;
+            let style = match section.heading {
+                Heading::Note => theme.note,
+                Heading::Suggestion => theme.suggestion,
+                Heading::Warning => theme.warning,
+                Heading::Custom(_) => theme.heading,
+            };
+            // Short labels keep the body on the same line; a custom title
+            // gets the body indented below it.
+            let label = format!("{}:", section.heading);
+            style.paint(f, &label)?;
+            let indent = match section.heading {
+                Heading::Custom(_) => {
+                    f.write_str("\n    ")?;
+                    4
+                }
+                _ => {
+                    f.write_str(" ")?;
+                    label.chars().count() + 1
+                }
+            };
+            write!(Hanging { inner: f, indent }, "{}", section.body)?;
+        }
+
+        // An error rebuilt from another process shows where it failed there.
+        let backtrace = self.backtrace();
+        let backtrace = match self.remote_backtrace() {
+            Some(remote) => Some(remote.to_string()),
+            None if backtrace.status() == BacktraceStatus::Captured => Some(backtrace.to_string()),
+            None => None,
+        };
+        if let (Some(backtrace), false) = (backtrace, nested) {
+            write!(f, "\n\n")?;
+            theme.heading.paint(f, "Stack backtrace:")?;
+            writeln!(f)?;
+            write_backtrace(f, &backtrace, theme)?;
+        }
+
+        Ok(())
+    }
+}
+
+// std renders each frame as "  N: symbol" followed by an optional
+// "at file:line:col" line; colour the two kinds of line separately.
+fn write_backtrace(f: &mut dyn Write, backtrace: &str, theme: &Theme) -> fmt::Result {
+    for (i, line) in backtrace.trim_end().lines().enumerate() {
+        if i > 0 {
+            writeln!(f)?;
+        }
+        let trimmed = line.trim_start();
+        let indent = &line[..line.len() - trimmed.len()];
+        f.write_str(indent)?;
+        match trimmed.split_once(": ") {
+            Some((number, symbol)) if number.bytes().all(|b| b.is_ascii_digit()) => {
+                theme.number.paint(f, number)?;
+                f.write_str(": ")?;
+                theme.frame.paint(f, symbol)?;
+            }
+            _ if trimmed.starts_with("at ") => theme.location.paint(f, trimmed)?,
+            _ => f.write_str(trimmed)?,
+        }
+    }
+    Ok(())
+}
+
+// The ANSI styles a report is rendered with; the built-in report uses none.
+pub(crate) struct Theme {
+    pub(crate) headline: Style,
+    pub(crate) heading: Style,
+    pub(crate) number: Style,
+    pub(crate) cause: Style,
+    pub(crate) note: Style,
+    pub(crate) suggestion: Style,
+    pub(crate) warning: Style,
+    pub(crate) frame: Style,
+    pub(crate) location: Style,
+}
+
+impl Theme {
+    pub(crate) const PLAIN: Theme = Theme {
+        headline: Style::NONE,
+        heading: Style::NONE,
+        number: Style::NONE,
+        cause: Style::NONE,
+        note: Style::NONE,
+        suggestion: Style::NONE,
+        warning: Style::NONE,
+        frame: Style::NONE,
+        location: Style::NONE,
+    };
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Style(pub(crate) &'static str);
+
+impl Style {
+    pub(crate) const NONE: Style = Style("");
+
+    pub(crate) fn paint<W>(self, w: &mut W, value: impl Display) -> fmt::Result
+    where
+        W: Write + ?Sized,
+    {
+        if self.0.is_empty() {
+            return write!(w, "{}", value);
+        }
+        write!(w, "\x1b[{}m{}\x1b[0m", self.0, value)
+    }
+}
+
+// A value that carries whole errors of its own, rendered as numbered reports
+// under a heading after the causes: the children of a `MultiError`, or the
+// earlier attempts of `retry`. A context value is asked only when its layer was
+// added with `Error::context_with_reports`.
+pub(crate) trait NestedReports {
+    fn heading(&self) -> &'static str;
+
+    // The errors to render, each with the number it is shown with.
+    fn reports(&self) -> Vec<(usize, &Error)>;
+
+    // How many further errors were left out.
+    fn hidden(&self) -> usize {
+        0
+    }
+}
+
+// Each error is rendered as a numbered, indented report of its own.
+fn write_sub_reports<'e>(
+    f: &mut dyn Write,
+    theme: &Theme,
+    heading: &str,
+    errors: impl Iterator<Item = (usize, &'e Error)>,
+) -> fmt::Result {
+    write!(f, "\n\n")?;
+    theme.heading.paint(f, heading)?;
+    for (n, error) in errors {
+        writeln!(f)?;
+        let mut indented = Indented {
+            inner: &mut *f,
+            number: Some(n),
+            style: theme.number,
+            started: false,
+            line_start: false,
+        };
+        error.write_report(&mut indented, theme, true)?;
+    }
+    Ok(())
+}
+
+// Indents every line of a cause, numbering the first one when there is more
+// than one cause to list. Blank lines are left without trailing whitespace.
+struct Indented<'a, D: ?Sized> {
+    inner: &'a mut D,
+    number: Option<usize>,
+    style: Style,
+    started: bool,
+    line_start: bool,
+}
+
+impl<D> Write for Indented<'_, D>
+where
+    D: Write + ?Sized,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.inner.write_char('\n')?;
+                self.line_start = true;
+            }
+            if line.is_empty() {
+                continue;
+            }
+
+            if !self.started {
+                self.started = true;
+                match self.number {
+                    Some(number) => {
+                        self.style
+                            .paint(self.inner, format_args!("{: >5}", number))?;
+                        self.inner.write_str(": ")?;
+                    }
+                    None => self.inner.write_str("    ")?,
+                }
+            } else if self.line_start {
+                if self.number.is_some() {
+                    self.inner.write_str("       ")?;
+                } else {
+                    self.inner.write_str("    ")?;
+                }
+            }
+            self.line_start = false;
+
+            self.inner.write_str(line)?;
+        }
+
+        Ok(())
+    }
+}
+
+// Indents every line after the first, so a multi-line body lines up under
+// its first line.
+struct Hanging<'a, D: ?Sized> {
+    inner: &'a mut D,
+    indent: usize,
+}
+
+impl<D> Write for Hanging<'_, D>
+where
+    D: Write + ?Sized,
+{
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                write!(self.inner, "\n{:1$}", "", self.indent)?;
+            }
+            self.inner.write_str(line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error as StdError, fmt, io};
+
+    use crate::Error;
+
+    // The `{:?}` report without the location lines and the backtrace, which
+    // depend on where the test runs.
+    fn report(error: &Error) -> String {
+        let report = format!("{:?}", error);
+        let report = report.split("\n\nStack backtrace:").next().unwrap();
+        report
+            .lines()
+            .filter(|line| !line.trim_start().starts_with("at src/"))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    #[derive(Debug)]
+    struct TwoLines;
+
+    impl fmt::Display for TwoLines {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("first line\n\nsecond line")
+        }
+    }
+
+    impl StdError for TwoLines {}
+
+    #[test]
+    fn display_shows_the_outermost_message_or_the_whole_chain() {
+        let error = Error::msg("root").context("middle").context("outer");
+        assert_eq!(format!("{}", error), "outer");
+        assert_eq!(format!("{:#}", error), "outer: middle: root");
+    }
+
+    #[test]
+    fn single_cause_is_not_numbered() {
+        let error = Error::msg("root").context("outer");
+        assert_eq!(report(&error), "outer\n\nCaused by:\n    root");
+    }
+
+    #[test]
+    fn several_causes_are_numbered() {
+        let error = Error::msg("root").context("middle").context("outer");
+        assert_eq!(
+            report(&error),
+            "outer\n\nCaused by:\n    0: middle\n    1: root"
+        );
+    }
+
+    #[test]
+    fn no_cause_is_just_the_message() {
+        assert_eq!(report(&Error::msg("alone")), "alone");
+    }
+
+    #[test]
+    fn multi_line_causes_are_indented() {
+        let error = Error::new(TwoLines).context("outer");
+        assert_eq!(
+            report(&error),
+            "outer\n\nCaused by:\n    first line\n\n    second line"
+        );
+
+        let error = Error::new(TwoLines).context("middle").context("outer");
+        assert_eq!(
+            report(&error),
+            "outer\n\nCaused by:\n    0: middle\n    1: first line\n\n       second line"
+        );
+    }
+
+    #[test]
+    fn alternate_debug_shows_the_wrapped_value() {
+        let error = Error::new(io::Error::other("inner"));
+        assert_eq!(
+            format!("{:#?}", error),
+            format!("{:#?}", io::Error::other("inner"))
+        );
+    }
+}
@@ -0,0 +1,57 @@
+use std::{
+    any::Any,
+    error::Error as StdError,
+    fmt::{self, Display},
+    sync::OnceLock,
+};
+
+use crate::Error;
+
+/// Renders the `{:?}` report of an [`Error`].
+///
+/// A handler is created by the installed hook whenever a new `Error` is
+/// created, so it can capture whatever extra state it needs at that point.
+/// Calling `{:?}` on the error from inside `debug` would recurse; delegate to
+/// [`DefaultHandler`] to fall back to the built-in report instead.
+pub trait ReportHandler: Any + Send + Sync {
+    fn debug(&self, error: &Error, f: &mut fmt::Formatter<'_>) -> fmt::Result;
+}
+
+/// Creates the [`ReportHandler`] for each new error.
+pub type ErrorHook =
+    Box<dyn Fn(&(dyn StdError + 'static)) -> Box<dyn ReportHandler> + Send + Sync + 'static>;
+
+static HOOK: OnceLock<ErrorHook> = OnceLock::new();
+
+/// Install the process-wide report hook. Only the first call succeeds; errors
+/// created before it was installed keep the built-in report.
+pub fn set_hook(hook: ErrorHook) -> Result<(), InstallError> {
+    HOOK.set(hook).map_err(|_| InstallError)
+}
+
+pub(crate) fn capture_handler(error: &(dyn StdError + 'static)) -> Option<Box<dyn ReportHandler>> {
+    HOOK.get().map(|hook| hook(error))
+}
+
+/// The built-in report: the outermost message, a "Caused by:" list and the
+/// backtrace, if one was captured.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct DefaultHandler;
+
+impl ReportHandler for DefaultHandler {
+    fn debug(&self, error: &Error, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        error.default_debug(f)
+    }
+}
+
+/// Returned by [`set_hook`] when a hook is already installed.
+#[derive(Debug)]
+pub struct InstallError;
+
+impl Display for InstallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("cannot install report hook, one is already installed")
+    }
+}
+
+impl StdError for InstallError {}
@@ -0,0 +1,205 @@
+use std::fmt::{self, Display};
+
+use crate::{multi::MultiError, Error};
+
+/// Consume an iterator of `Result`s without stopping at the first failure.
+///
+/// Every error is kept in a [`MultiError`]. The `_indexed` variants also wrap
+/// each error in an [`ItemIndex`] context layer naming its position in the
+/// iterator, so `downcast_ref::<ItemIndex>()` on each child finds it again.
+pub trait CollectAll<T, E>: Iterator<Item = Result<T, E>> + Sized {
+    /// Every success, or every error as one [`MultiError`].
+    fn collect_all<C>(self) -> Result<C, Error>
+    where
+        C: FromIterator<T>;
+
+    fn collect_all_indexed<C>(self) -> Result<C, Error>
+    where
+        C: FromIterator<T>;
+
+    /// Every success and every error, side by side.
+    fn partition_errors<C>(self) -> (C, MultiError)
+    where
+        C: Default + Extend<T>;
+
+    fn partition_errors_indexed<C>(self) -> (C, MultiError)
+    where
+        C: Default + Extend<T>;
+}
+
+impl<I, T, E> CollectAll<T, E> for I
+where
+    I: Iterator<Item = Result<T, E>>,
+    E: Into<Error>,
+{
+    #[track_caller]
+    fn collect_all<C>(self) -> Result<C, Error>
+    where
+        C: FromIterator<T>,
+    {
+        collect(self, false)
+    }
+
+    #[track_caller]
+    fn collect_all_indexed<C>(self) -> Result<C, Error>
+    where
+        C: FromIterator<T>,
+    {
+        collect(self, true)
+    }
+
+    #[track_caller]
+    fn partition_errors<C>(self) -> (C, MultiError)
+    where
+        C: Default + Extend<T>,
+    {
+        let mut oks = C::default();
+        let errors = gather(self, false, &mut oks);
+        (oks, errors)
+    }
+
+    #[track_caller]
+    fn partition_errors_indexed<C>(self) -> (C, MultiError)
+    where
+        C: Default + Extend<T>,
+    {
+        let mut oks = C::default();
+        let errors = gather(self, true, &mut oks);
+        (oks, errors)
+    }
+}
+
+/// The position of a failed item, added as context by the `_indexed` methods
+/// of [`CollectAll`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ItemIndex(pub usize);
+
+impl Display for ItemIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "item {}", self.0)
+    }
+}
+
+#[track_caller]
+fn collect<I, T, E, C>(iter: I, indexed: bool) -> Result<C, Error>
+where
+    I: Iterator<Item = Result<T, E>>,
+    E: Into<Error>,
+    C: FromIterator<T>,
+{
+    let mut oks = Vec::new();
+    let errors = gather(iter, indexed, &mut oks);
+    errors.into_result()?;
+    Ok(oks.into_iter().collect())
+}
+
+#[track_caller]
+fn gather<I, T, E, C>(iter: I, indexed: bool, oks: &mut C) -> MultiError
+where
+    I: Iterator<Item = Result<T, E>>,
+    E: Into<Error>,
+    C: Extend<T>,
+{
+    let mut errors = MultiError::new();
+    // `Context` is implemented for the `Result`, and by the time there is an
+    // error to index it has been matched out, so this calls `Error::context`
+    // directly, as the `Context` impl for `Result<T, Error>` does.
+    for (index, result) in iter.enumerate() {
+        match result {
+            Ok(ok) => oks.extend(Some(ok)),
+            Err(error) if indexed => errors.push(error.into().context(ItemIndex(index))),
+            Err(error) => errors.push(error),
+        }
+    }
+    errors
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::{CollectAll, ItemIndex};
+    use crate::{Error, MultiError};
+
+    fn items() -> Vec<Result<u8, io::Error>> {
+        vec![
+            Ok(1),
+            Err(io::Error::from(io::ErrorKind::NotFound)),
+            Ok(2),
+            Err(io::Error::from(io::ErrorKind::PermissionDenied)),
+            Ok(3),
+        ]
+    }
+
+    fn children(error: &Error) -> &MultiError {
+        error.downcast_ref::<MultiError>().unwrap()
+    }
+
+    #[test]
+    fn collect_all_returns_every_success() {
+        let oks: Vec<u8> = vec![Ok::<_, io::Error>(1), Ok(2)]
+            .into_iter()
+            .collect_all()
+            .unwrap();
+        assert_eq!(oks, [1, 2]);
+
+        let oks: Vec<u8> = Vec::<Result<u8, Error>>::new()
+            .into_iter()
+            .collect_all()
+            .unwrap();
+        assert!(oks.is_empty());
+    }
+
+    #[test]
+    fn collect_all_keeps_every_error() {
+        let error = items().into_iter().collect_all::<Vec<u8>>().unwrap_err();
+        let children = children(&error);
+        assert_eq!(children.len(), 2);
+        let kinds: Vec<_> = children
+            .iter()
+            .map(|child| child.downcast_ref::<io::Error>().unwrap().kind())
+            .collect();
+        assert_eq!(
+            kinds,
+            [io::ErrorKind::NotFound, io::ErrorKind::PermissionDenied]
+        );
+        assert!(children.iter().all(|child| !child.is::<ItemIndex>()));
+    }
+
+    #[test]
+    fn partition_errors_keeps_every_ok() {
+        let (oks, errors): (Vec<u8>, _) = items().into_iter().partition_errors();
+        assert_eq!(oks, [1, 2, 3]);
+        assert_eq!(errors.len(), 2);
+
+        let (oks, errors): (Vec<u8>, _) = vec![Ok::<_, Error>(1)].into_iter().partition_errors();
+        assert_eq!(oks, [1]);
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn indexed_variants_name_the_position_of_each_error() {
+        let iter = items().into_iter();
+        let line = line!() + 1;
+        let error = iter.collect_all_indexed::<Vec<u8>>().unwrap_err();
+        let indices: Vec<_> = children(&error)
+            .iter()
+            .map(|child| child.downcast_ref::<ItemIndex>().copied())
+            .collect();
+        assert_eq!(indices, [Some(ItemIndex(1)), Some(ItemIndex(3))]);
+        let first = children(&error).iter().next().unwrap();
+        assert_eq!(format!("{:#}", first), "item 1: entity not found");
+        assert_eq!(first.location().file(), file!());
+        assert_eq!(first.location().line(), line);
+
+        let (oks, errors): (Vec<u8>, _) = items().into_iter().partition_errors_indexed();
+        assert_eq!(oks, [1, 2, 3]);
+        assert_eq!(
+            errors
+                .iter()
+                .map(|child| child.downcast_ref::<ItemIndex>().unwrap().0)
+                .collect::<Vec<_>>(),
+            [1, 3]
+        );
+    }
+}
@@ -0,0 +1,676 @@
+use std::{
+    backtrace::BacktraceStatus,
+    fmt::{self, Display, Write},
+};
+
+use crate::{
+    error::{LinkKind, SourceLocation},
+    multi::MultiError,
+    Error,
+};
+
+/// The `version` field written by [`Error::to_json`]. [`Error::from_json`]
+/// reads every version from 1 up to this one.
+pub const JSON_VERSION: u32 = 2;
+
+impl Error {
+    /// Serialize the full report as a single line of JSON.
+    ///
+    /// The object has these fields, and any addition or change to them bumps
+    /// [`JSON_VERSION`]. Fields added after version 1 are marked with the
+    /// version that added them and are optional when reading:
+    ///
+    /// - `version`: [`JSON_VERSION`].
+    /// - `message`: the outermost message.
+    /// - `chain`: one object per entry of [`chain()`](Error::chain), outermost
+    ///   first, with `kind` (`"context"`, `"error"` or `"source"`), `message`,
+    ///   `type` and `location` (`file`, `line`, `column`). `type` and `location`
+    ///   are `null` for the sources of a foreign error. The entry of a
+    ///   [`MultiError`](crate::MultiError) also has `errors`, a full report
+    ///   like this one for each child, and the `limit` it renders with
+    ///   (version 2).
+    /// - `attachments`: `type` and `display` of every attachment in the order
+    ///   they were added; `display` is `null` unless it was attached with
+    ///   [`attach_printable`](Error::attach_printable).
+    /// - `sections`: `title` and `body` of every note and section.
+    /// - `backtrace`: `status` (`"captured"`, `"disabled"` or
+    ///   `"unsupported"`) and `frames`, each with `index`, `symbol` and a
+    ///   `location` that is `null` when std did not resolve one.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_json(&mut out)
+            .expect("writing to a String cannot fail");
+        out
+    }
+
+    fn write_json(&self, out: &mut String) -> fmt::Result {
+        write!(out, "{{\"version\":{},\"message\":", JSON_VERSION)?;
+        string(out, &**self)?;
+
+        out.push_str(",\"chain\":[");
+        for (i, (cause, link)) in self.chain().zip(self.links()).enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            let kind = match link.kind {
+                LinkKind::Context => "context",
+                LinkKind::Error => "error",
+                LinkKind::Source => "source",
+            };
+            write!(out, "{{\"kind\":\"{}\",\"message\":", kind)?;
+            string(out, cause)?;
+            out.push_str(",\"type\":");
+            match link.type_name {
+                Some(type_name) => string(out, type_name)?,
+                None => out.push_str("null"),
+            }
+            out.push_str(",\"location\":");
+            match link.location {
+                Some(location) => source_location(out, location)?,
+                None => out.push_str("null"),
+            }
+            if let Some(multi) = MultiError::of(cause) {
+                out.push_str(",\"errors\":[");
+                for (i, error) in multi.iter().enumerate() {
+                    if i > 0 {
+                        out.push(',');
+                    }
+                    error.write_json(out)?;
+                }
+                write!(out, "],\"limit\":{}", multi.limit())?;
+            }
+            out.push('}');
+        }
+
+        out.push_str("],\"attachments\":[");
+        for (i, attachment) in self.attachments().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str("{\"type\":");
+            string(out, attachment.type_name())?;
+            out.push_str(",\"display\":");
+            if attachment.is_printable() {
+                string(out, attachment)?;
+            } else {
+                out.push_str("null");
+            }
+            out.push('}');
+        }
+
+        out.push_str("],\"sections\":[");
+        for (i, section) in self.sections().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            out.push_str("{\"title\":");
+            string(out, &section.heading)?;
+            out.push_str(",\"body\":");
+            string(out, &section.body)?;
+            out.push('}');
+        }
+
+        // A backtrace from another process is the one that matters.
+        let backtrace = self.backtrace();
+        let (status, frames_text) = match (self.remote_backtrace(), backtrace.status()) {
+            (Some(remote), _) => ("captured", Some(remote.to_string())),
+            (None, BacktraceStatus::Captured) => ("captured", Some(backtrace.to_string())),
+            (None, BacktraceStatus::Disabled) => ("disabled", None),
+            (None, _) => ("unsupported", None),
+        };
+        write!(
+            out,
+            "],\"backtrace\":{{\"status\":\"{}\",\"frames\":[",
+            status
+        )?;
+        if let Some(text) = frames_text {
+            frames(out, &text)?;
+        }
+        out.push_str("]}}");
+        Ok(())
+    }
+}
+
+fn source_location(out: &mut String, location: SourceLocation<'_>) -> fmt::Result {
+    out.push_str("{\"file\":");
+    string(out, location.file)?;
+    out.push_str(",\"line\":");
+    number(out, location.line)?;
+    out.push_str(",\"column\":");
+    number(out, location.column)?;
+    out.push('}');
+    Ok(())
+}
+
+fn number(out: &mut String, value: Option<u32>) -> fmt::Result {
+    match value {
+        Some(value) => write!(out, "{}", value),
+        None => {
+            out.push_str("null");
+            Ok(())
+        }
+    }
+}
+
+// std renders each frame as "N: symbol", optionally followed by an
+// "at file:line:col" line.
+fn frames(out: &mut String, backtrace: &str) -> fmt::Result {
+    let mut count = 0;
+    let mut open = false;
+    for line in backtrace.lines().map(str::trim) {
+        if let Some((index, symbol)) = line.split_once(": ") {
+            if let Ok(index) = index.parse::<usize>() {
+                if open {
+                    out.push_str(",\"location\":null}");
+                }
+                if count > 0 {
+                    out.push(',');
+                }
+                write!(out, "{{\"index\":{},\"symbol\":", index)?;
+                string(out, symbol)?;
+                count += 1;
+                open = true;
+                continue;
+            }
+        }
+        if let (true, Some(location)) = (open, line.strip_prefix("at ")) {
+            out.push_str(",\"location\":");
+            source_location(out, SourceLocation::parse(location))?;
+            out.push('}');
+            open = false;
+        }
+    }
+    if open {
+        out.push_str(",\"location\":null}");
+    }
+    Ok(())
+}
+
+// Writes `value` as a JSON string, escaping it as it is formatted.
+fn string(out: &mut String, value: impl Display) -> fmt::Result {
+    out.push('"');
+    write!(Escaped(out), "{}", value)?;
+    out.push('"');
+    Ok(())
+}
+
+struct Escaped<'a>(&'a mut String);
+
+impl Write for Escaped<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for c in s.chars() {
+            match c {
+                '"' => self.0.push_str("\\\""),
+                '\\' => self.0.push_str("\\\\"),
+                '\n' => self.0.push_str("\\n"),
+                '\r' => self.0.push_str("\\r"),
+                '\t' => self.0.push_str("\\t"),
+                c if c < ' ' => write!(self.0, "\\u{:04x}", c as u32)?,
+                c => self.0.push(c),
+            }
+        }
+        Ok(())
+    }
+}
+
+// Just enough of a JSON reader to load back what `to_json` writes.
+pub(crate) enum Value {
+    Null,
+    // The schema has no booleans; they are only accepted so that any valid
+    // JSON parses.
+    Bool,
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub(crate) fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub(crate) fn as_u32(&self) -> Option<u32> {
+        match *self {
+            Value::Number(n) if n >= 0.0 && n <= u32::MAX as f64 && n.fract() == 0.0 => {
+                Some(n as u32)
+            }
+            _ => None,
+        }
+    }
+
+    pub(crate) fn is_null(&self) -> bool {
+        matches!(self, Value::Null)
+    }
+}
+
+/// Returned by [`Error::from_json`] when the input is not a report written by
+/// [`Error::to_json`].
+#[derive(Debug)]
+pub struct FromJsonError {
+    kind: FromJsonErrorKind,
+}
+
+#[derive(Debug)]
+enum FromJsonErrorKind {
+    Syntax {
+        offset: usize,
+        expected: &'static str,
+    },
+    Version(Option<u32>),
+    Field(&'static str),
+}
+
+impl FromJsonError {
+    pub(crate) fn field(name: &'static str) -> Self {
+        FromJsonError {
+            kind: FromJsonErrorKind::Field(name),
+        }
+    }
+
+    pub(crate) fn version(version: Option<u32>) -> Self {
+        FromJsonError {
+            kind: FromJsonErrorKind::Version(version),
+        }
+    }
+}
+
+impl Display for FromJsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            FromJsonErrorKind::Syntax { offset, expected } => {
+                write!(f, "invalid JSON at byte {}: expected {}", offset, expected)
+            }
+            FromJsonErrorKind::Version(Some(version)) => write!(
+                f,
+                "unsupported error report version {}, expected at most {}",
+                version, JSON_VERSION
+            ),
+            FromJsonErrorKind::Version(None) => f.write_str("error report has no version"),
+            FromJsonErrorKind::Field(name) => {
+                write!(f, "missing or invalid field `{}` in error report", name)
+            }
+        }
+    }
+}
+
+impl std::error::Error for FromJsonError {}
+
+pub(crate) fn parse(input: &str) -> Result<Value, FromJsonError> {
+    let mut parser = Parser {
+        input,
+        pos: 0,
+        depth: 0,
+    };
+    let value = parser.value()?;
+    parser.whitespace();
+    if parser.pos != input.len() {
+        return Err(parser.error("end of input"));
+    }
+    Ok(value)
+}
+
+// Deeper input is rejected rather than risking the stack; a report nests
+// far less than this.
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, expected: &'static str) -> FromJsonError {
+        FromJsonError {
+            kind: FromJsonErrorKind::Syntax {
+                offset: self.pos,
+                expected,
+            },
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.input.as_bytes().get(self.pos).copied()
+    }
+
+    fn whitespace(&mut self) {
+        while let Some(b' ' | b'\t' | b'\n' | b'\r') = self.peek() {
+            self.pos += 1;
+        }
+    }
+
+    fn eat(&mut self, byte: u8) -> bool {
+        self.whitespace();
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn keyword(&mut self, keyword: &'static str, value: Value) -> Result<Value, FromJsonError> {
+        if self.input[self.pos..].starts_with(keyword) {
+            self.pos += keyword.len();
+            Ok(value)
+        } else {
+            Err(self.error(keyword))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, FromJsonError> {
+        self.whitespace();
+        match self.peek() {
+            Some(b'n') => self.keyword("null", Value::Null),
+            Some(b't') => self.keyword("true", Value::Bool),
+            Some(b'f') => self.keyword("false", Value::Bool),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'[') => self.nested(Parser::array),
+            Some(b'{') => self.nested(Parser::object),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            _ => Err(self.error("a value")),
+        }
+    }
+
+    fn nested(
+        &mut self,
+        parse: fn(&mut Self) -> Result<Value, FromJsonError>,
+    ) -> Result<Value, FromJsonError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("less deeply nested input"));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn array(&mut self) -> Result<Value, FromJsonError> {
+        self.pos += 1;
+        let mut values = Vec::new();
+        if self.eat(b']') {
+            return Ok(Value::Array(values));
+        }
+        loop {
+            values.push(self.value()?);
+            if self.eat(b']') {
+                return Ok(Value::Array(values));
+            }
+            if !self.eat(b',') {
+                return Err(self.error("`,` or `]`"));
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, FromJsonError> {
+        self.pos += 1;
+        let mut fields = Vec::new();
+        if self.eat(b'}') {
+            return Ok(Value::Object(fields));
+        }
+        loop {
+            self.whitespace();
+            if self.peek() != Some(b'"') {
+                return Err(self.error("a string key"));
+            }
+            let key = self.string()?;
+            if !self.eat(b':') {
+                return Err(self.error("`:`"));
+            }
+            fields.push((key, self.value()?));
+            if self.eat(b'}') {
+                return Ok(Value::Object(fields));
+            }
+            if !self.eat(b',') {
+                return Err(self.error("`,` or `}`"));
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, FromJsonError> {
+        let start = self.pos;
+        while let Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9') = self.peek() {
+            self.pos += 1;
+        }
+        match self.input[start..self.pos].parse() {
+            Ok(number) => Ok(Value::Number(number)),
+            Err(_) => {
+                self.pos = start;
+                Err(self.error("a number"))
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String, FromJsonError> {
+        self.pos += 1;
+        let mut out = String::new();
+        loop {
+            let rest = &self.input[self.pos..];
+            let end = match rest.find(['"', '\\']) {
+                Some(end) => end,
+                None => {
+                    self.pos = self.input.len();
+                    return Err(self.error("`\"`"));
+                }
+            };
+            out.push_str(&rest[..end]);
+            self.pos += end + 1;
+            if rest.as_bytes()[end] == b'"' {
+                return Ok(out);
+            }
+            let escaped = match self.peek() {
+                Some(b'"') => '"',
+                Some(b'\\') => '\\',
+                Some(b'/') => '/',
+                Some(b'b') => '\u{8}',
+                Some(b'f') => '\u{c}',
+                Some(b'n') => '\n',
+                Some(b'r') => '\r',
+                Some(b't') => '\t',
+                Some(b'u') => {
+                    out.push(self.unicode_escape()?);
+                    continue;
+                }
+                _ => return Err(self.error("an escape sequence")),
+            };
+            self.pos += 1;
+            out.push(escaped);
+        }
+    }
+
+    // `\uXXXX` at `pos - 1`, possibly the first half of a surrogate pair.
+    fn unicode_escape(&mut self) -> Result<char, FromJsonError> {
+        let high = self.hex4()?;
+        let code = if (0xd800..0xdc00).contains(&high) {
+            if !self.input[self.pos..].starts_with("\\u") {
+                return Err(self.error("a low surrogate"));
+            }
+            self.pos += 1;
+            let low = self.hex4()?;
+            if !(0xdc00..0xe000).contains(&low) {
+                return Err(self.error("a low surrogate"));
+            }
+            0x10000 + ((high - 0xd800) << 10) + (low - 0xdc00)
+        } else {
+            high
+        };
+        char::from_u32(code).ok_or_else(|| self.error("a unicode scalar value"))
+    }
+
+    // Skips the `u` at `pos` and reads the four hex digits after it.
+    fn hex4(&mut self) -> Result<u32, FromJsonError> {
+        self.pos += 1;
+        let digits = self
+            .input
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| self.error("four hex digits"))?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| self.error("four hex digits"))?;
+        self.pos += 4;
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{backtrace::BacktraceStatus, error::Error as StdError, fmt, io};
+
+    use super::{parse, Value};
+    use crate::Error;
+
+    #[derive(Debug)]
+    struct Outer(io::Error);
+
+    impl fmt::Display for Outer {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("outer")
+        }
+    }
+
+    impl StdError for Outer {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    fn report(error: &Error) -> Value {
+        parse(&error.to_json()).expect("to_json writes valid JSON")
+    }
+
+    fn field<'a>(value: &'a Value, name: &str) -> &'a Value {
+        value
+            .get(name)
+            .unwrap_or_else(|| panic!("no field `{}`", name))
+    }
+
+    fn str_field<'a>(value: &'a Value, name: &str) -> &'a str {
+        field(value, name).as_str().unwrap()
+    }
+
+    #[test]
+    fn strings_are_escaped() {
+        let message = "quote \" backslash \\ newline \n tab \t bell \u{7} caf\u{e9} \u{2713}";
+        let json = Error::msg(message).to_json();
+        assert!(json
+            .contains(r#""message":"quote \" backslash \\ newline \n tab \t bell \u0007 café ✓""#));
+        assert_eq!(str_field(&report(&Error::msg(message)), "message"), message);
+    }
+
+    #[test]
+    fn foreign_sources_have_no_type_or_location() {
+        let error = Error::new(Outer(io::Error::other("inner"))).context("ctx");
+        let report = report(&error);
+        let chain = field(&report, "chain").as_array().unwrap();
+        assert_eq!(chain.len(), 3);
+
+        let kinds: Vec<_> = chain.iter().map(|entry| str_field(entry, "kind")).collect();
+        assert_eq!(kinds, ["context", "error", "source"]);
+        assert_eq!(str_field(&chain[0], "type"), "&str");
+        assert!(str_field(&chain[1], "type").ends_with("Outer"));
+        assert_eq!(str_field(field(&chain[1], "location"), "file"), file!());
+        assert_eq!(str_field(&chain[2], "message"), "inner");
+        assert!(field(&chain[2], "type").is_null());
+        assert!(field(&chain[2], "location").is_null());
+    }
+
+    #[test]
+    fn only_printable_attachments_have_a_display() {
+        let error = Error::msg("root").attach(7u8).attach_printable("shown");
+        let report = report(&error);
+        let attachments = field(&report, "attachments").as_array().unwrap();
+        assert_eq!(attachments.len(), 2);
+        assert_eq!(str_field(&attachments[0], "type"), "u8");
+        assert!(field(&attachments[0], "display").is_null());
+        assert_eq!(str_field(&attachments[1], "type"), "&str");
+        assert_eq!(str_field(&attachments[1], "display"), "shown");
+    }
+
+    #[test]
+    fn sections_keep_their_titles() {
+        let error = Error::msg("root")
+            .note("a note")
+            .section("Details", "line one\nline two");
+        let report = report(&error);
+        let sections = field(&report, "sections").as_array().unwrap();
+        assert_eq!(sections.len(), 2);
+        assert_eq!(str_field(&sections[0], "title"), "Note");
+        assert_eq!(str_field(&sections[0], "body"), "a note");
+        assert_eq!(str_field(&sections[1], "title"), "Details");
+        assert_eq!(str_field(&sections[1], "body"), "line one\nline two");
+    }
+
+    #[test]
+    fn backtrace_status_follows_the_capture() {
+        let error = Error::msg("root");
+        let expected = match error.backtrace().status() {
+            BacktraceStatus::Captured => "captured",
+            BacktraceStatus::Disabled => "disabled",
+            _ => "unsupported",
+        };
+        let report = report(&error);
+        assert_eq!(str_field(field(&report, "backtrace"), "status"), expected);
+    }
+
+    #[test]
+    fn remote_backtrace_frames_round_trip() {
+        let json = format!(
+            concat!(
+                r#"{{"version":{},"message":"m","chain":[{{"kind":"error","message":"m","type":null,"location":null}}],"#,
+                r#""attachments":[],"sections":[],"backtrace":{{"status":"captured","frames":["#,
+                r#"{{"index":0,"symbol":"app::main","location":{{"file":"src/main.rs","line":3,"column":5}}}},"#,
+                r#"{{"index":1,"symbol":"std::rt::lang_start","location":null}}]}}}}"#
+            ),
+            super::JSON_VERSION
+        );
+        let error = Error::from_json(&json).unwrap();
+        assert_eq!(error.to_json(), json);
+
+        let disabled = json.replace(r#""captured""#, r#""disabled""#);
+        let disabled = &disabled[..disabled.find(r#""frames":["#).unwrap()];
+        let disabled = format!("{}\"frames\":[]}}}}", disabled);
+        assert_eq!(Error::from_json(&disabled).unwrap().to_json(), disabled);
+    }
+
+    #[test]
+    fn round_trip_keeps_chain_attachments_and_sections() {
+        let error = Error::new(Outer(io::Error::other("inner")))
+            .context("middle")
+            .attach(1u8)
+            .attach_printable("printable")
+            .note("a note")
+            .context("outer")
+            .warning("careful");
+
+        let rebuilt = Error::from_json(&error.to_json()).unwrap();
+        assert_eq!(rebuilt.to_json(), error.to_json());
+        assert_eq!(format!("{:#}", rebuilt), format!("{:#}", error));
+        assert_eq!(rebuilt.chain().count(), 4);
+
+        let without_backtrace = |error: &Error| {
+            let report = format!("{:?}", error);
+            report
+                .split("\n\nStack backtrace:")
+                .next()
+                .unwrap()
+                .to_owned()
+        };
+        assert_eq!(without_backtrace(&rebuilt), without_backtrace(&error));
+    }
+}
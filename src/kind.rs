@@ -0,0 +1,158 @@
+// Tagged dispatch for `regardless!($expr)`, picked by autoref specialisation:
+//
+//     match $err {
+//         error => (&error).regardless_kind().wrap(error),
+//     }
+//
+// Anything that is already `Into<Error>` (std errors and `Error` itself) is
+// converted with `From`, a `Box<dyn StdError + Send + Sync>` keeps its
+// `source()` chain, and any other `Display + Debug` value becomes the message.
+
+use std::{
+    error::Error as StdError,
+    fmt::{Debug, Display},
+};
+
+use crate::Error;
+
+pub struct Adhoc;
+
+pub trait AdhocKind: Sized {
+    #[inline]
+    fn regardless_kind(&self) -> Adhoc {
+        Adhoc
+    }
+}
+
+impl<T> AdhocKind for &T where T: ?Sized + Display + Debug + Send + Sync + 'static {}
+
+impl Adhoc {
+    #[cold]
+    #[track_caller]
+    pub fn wrap<M>(self, message: M) -> Error
+    where
+        M: Display + Debug + Send + Sync + 'static,
+    {
+        Error::from_adhoc(message)
+    }
+}
+
+pub struct Trait;
+
+pub trait TraitKind: Sized {
+    #[inline]
+    fn regardless_kind(&self) -> Trait {
+        Trait
+    }
+}
+
+impl<E> TraitKind for E where E: Into<Error> {}
+
+impl Trait {
+    #[cold]
+    #[track_caller]
+    pub fn wrap<E>(self, error: E) -> Error
+    where
+        E: Into<Error>,
+    {
+        error.into()
+    }
+}
+
+pub struct Boxed;
+
+pub trait BoxedKind: Sized {
+    #[inline]
+    fn regardless_kind(&self) -> Boxed {
+        Boxed
+    }
+}
+
+impl BoxedKind for Box<dyn StdError + Send + Sync> {}
+
+impl Boxed {
+    #[cold]
+    #[track_caller]
+    pub fn wrap(self, error: Box<dyn StdError + Send + Sync>) -> Error {
+        Error::from_boxed(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{error::Error as StdError, fmt, io};
+
+    use crate::{regardless, Error};
+
+    #[derive(Debug)]
+    struct Outer(io::Error);
+
+    impl fmt::Display for Outer {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("outer")
+        }
+    }
+
+    impl StdError for Outer {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(&self.0)
+        }
+    }
+
+    #[test]
+    fn std_error_is_wrapped_as_itself() {
+        let error = regardless!(Outer(io::Error::other("inner")));
+        assert!(error.is::<Outer>());
+        assert_eq!(format!("{:#}", error), "outer: inner");
+        assert_eq!(error.chain().count(), 2);
+    }
+
+    #[test]
+    fn boxed_error_keeps_its_source_chain() {
+        let boxed: Box<dyn StdError + Send + Sync> = Box::new(Outer(io::Error::other("inner")));
+        let error = regardless!(boxed);
+        assert!(error.is::<Box<dyn StdError + Send + Sync>>());
+        assert_eq!(format!("{:#}", error), "outer: inner");
+        assert_eq!(error.chain().count(), 2);
+    }
+
+    #[test]
+    fn display_value_becomes_the_message() {
+        let status = 404u16;
+        let error = regardless!(status);
+        assert_eq!(error.to_string(), "404");
+        assert_eq!(error.downcast_ref::<u16>(), Some(&404));
+        assert!(error.source().is_none());
+    }
+
+    #[test]
+    fn existing_error_is_passed_through() {
+        let original = Error::msg("root").context("outer");
+        let location = original.location();
+        let error = regardless!(original);
+        assert_eq!(error.chain().count(), 2);
+        assert_eq!(format!("{:#}", error), "outer: root");
+        assert_eq!(error.location(), location);
+    }
+
+    #[test]
+    fn literal_without_arguments_is_not_copied() {
+        let error = regardless!("plain message");
+        assert_eq!(error.downcast_ref::<&str>(), Some(&"plain message"));
+        assert!(!error.is::<String>());
+    }
+
+    #[test]
+    fn inline_captures_are_formatted() {
+        let path = "config.toml";
+        let error = regardless!("missing {path}");
+        assert_eq!(error.to_string(), "missing config.toml");
+        assert_eq!(
+            error.downcast_ref::<String>().map(String::as_str),
+            Some("missing config.toml")
+        );
+
+        let error = regardless!("missing {}, {}", path, 2);
+        assert_eq!(error.to_string(), "missing config.toml, 2");
+    }
+}
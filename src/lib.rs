@@ -1,90 +1,281 @@
 use std::{
+    convert::Infallible,
     error::Error as StdError,
     fmt::{Debug, Display},
-    ops::{Deref, DerefMut},
+    str::FromStr,
 };
 
+mod attachment;
+mod chain;
+mod compat;
+mod context;
+mod ensure;
+mod error;
+mod fmt;
+mod hook;
+mod iter;
+mod json;
+mod kind;
+mod multi;
+mod pretty;
+mod ptr;
+mod remote;
+mod report;
+mod retry;
+mod scope;
+mod section;
+mod wrapper;
+
+pub use chain::Chain;
+pub use compat::Compat;
+use error::ErrorImpl;
+pub use hook::{set_hook, DefaultHandler, ErrorHook, InstallError, ReportHandler};
+pub use iter::{CollectAll, ItemIndex};
+pub use json::{FromJsonError, JSON_VERSION};
+pub use multi::MultiError;
+pub use pretty::{ColorChoice, PrettyHandler};
+use ptr::Own;
+pub use remote::{RemoteAttachment, RemoteError, RemoteLocation};
+pub use report::Report;
+pub use retry::{retry, Attempts, Clock, RetryPolicy, SystemClock, Transient};
+pub use scope::{scope, ScopeGuard};
+pub use section::Section;
+
 #[macro_export]
 macro_rules! regardless {
+    ($s:literal $(,)?) => {
+        $crate::__private::format_err(::std::format_args!($s))
+    };
+    ($fstring:literal, $($arg:tt)*) => {
+        $crate::__private::format_err(::std::format_args!($fstring, $($arg)*))
+    };
+    ($err:expr $(,)?) => {
+        match $err {
+            error => {
+                #[allow(unused_imports)]
+                use $crate::__private::kind::{AdhocKind as _, BoxedKind as _, TraitKind as _};
+                (&error).regardless_kind().wrap(error)
+            }
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! bail {
     ($s:literal) => {
-        Error::from_str($s)
+        return ::std::result::Result::Err($crate::regardless!($s))
     };
     ($fstring:literal, $($arg:tt)*) => {
-        Error::from_string(format!($fstring, $($arg)*))
+        return ::std::result::Result::Err($crate::regardless!($fstring, $($arg)*))
+    };
+    ($err:expr) => {
+        return ::std::result::Result::Err($crate::regardless!($err))
     };
 }
 
+#[macro_export]
+macro_rules! ensure {
+    ($cond:expr, $s:literal) => {
+        if !$cond {
+            $crate::bail!($s)
+        }
+    };
+    ($cond:expr, $fstring:literal, $($arg:tt)*) => {
+        if !$cond {
+            $crate::bail!($fstring, $($arg)*)
+        }
+    };
+    ($cond:expr, $err:expr) => {
+        if !$cond {
+            $crate::bail!($err)
+        }
+    };
+    ($($cond:tt)+) => {
+        $crate::__ensure_cmp!(@lhs [$($cond)+] [
+            ~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~~
+        ] [] $($cond)+)
+    };
+}
+
+// Splits the condition of a message-less `ensure!` at its top-level comparison
+// operator so both operands can be shown on failure. Anything that would bind
+// looser than a comparison (`&&`, `||`, ranges, assignment) or a second
+// comparison falls back to reporting the condition as written.
+//
+// Each step munches one token and spends one `~` of the budget passed in by
+// `ensure!`, so a long condition falls back too instead of running into the
+// recursion limit. A trailing comma ends the condition.
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __ensure_cmp {
+    (@lhs $orig:tt [] $($rest:tt)*) => { $crate::__ensure_cmp!(@plain $orig) };
+    (@lhs $orig:tt $fuel:tt [$($lhs:tt)+] == $($rhs:tt)+) => { $crate::__ensure_cmp!(@rhs $orig $fuel [$($lhs)+] [==] [] $($rhs)+) };
+    (@lhs $orig:tt $fuel:tt [$($lhs:tt)+] != $($rhs:tt)+) => { $crate::__ensure_cmp!(@rhs $orig $fuel [$($lhs)+] [!=] [] $($rhs)+) };
+    (@lhs $orig:tt $fuel:tt [$($lhs:tt)+] <= $($rhs:tt)+) => { $crate::__ensure_cmp!(@rhs $orig $fuel [$($lhs)+] [<=] [] $($rhs)+) };
+    (@lhs $orig:tt $fuel:tt [$($lhs:tt)+] >= $($rhs:tt)+) => { $crate::__ensure_cmp!(@rhs $orig $fuel [$($lhs)+] [>=] [] $($rhs)+) };
+    (@lhs $orig:tt $fuel:tt [$($lhs:tt)+] < $($rhs:tt)+) => { $crate::__ensure_cmp!(@rhs $orig $fuel [$($lhs)+] [<] [] $($rhs)+) };
+    (@lhs $orig:tt $fuel:tt [$($lhs:tt)+] > $($rhs:tt)+) => { $crate::__ensure_cmp!(@rhs $orig $fuel [$($lhs)+] [>] [] $($rhs)+) };
+    (@lhs $orig:tt $fuel:tt $lhs:tt && $($rest:tt)*) => { $crate::__ensure_cmp!(@plain $orig) };
+    (@lhs $orig:tt $fuel:tt $lhs:tt || $($rest:tt)*) => { $crate::__ensure_cmp!(@plain $orig) };
+    (@lhs $orig:tt $fuel:tt $lhs:tt ,) => { $crate::__ensure_cmp!(@plain $orig) };
+    (@lhs $orig:tt [~ $($fuel:tt)*] [$($lhs:tt)*] $next:tt $($rest:tt)*) => { $crate::__ensure_cmp!(@lhs $orig [$($fuel)*] [$($lhs)* $next] $($rest)*) };
+    (@lhs $orig:tt $fuel:tt $lhs:tt) => { $crate::__ensure_cmp!(@plain $orig) };
+
+    (@rhs $orig:tt [] $($rest:tt)*) => { $crate::__ensure_cmp!(@plain $orig) };
+    (@rhs $orig:tt $fuel:tt $lhs:tt $op:tt $rhs:tt == $($rest:tt)*) => { $crate::__ensure_cmp!(@plain $orig) };
+    (@rhs $orig:tt $fuel:tt $lhs:tt $op:tt $rhs:tt != $($rest:tt)*) => { $crate::__ensure_cmp!(@plain $orig) };
+    (@rhs $orig:tt $fuel:tt $lhs:tt $op:tt $rhs:tt <= $($rest:tt)*) => { $crate::__ensure_cmp!(@plain $orig) };
+    (@rhs $orig:tt $fuel:tt $lhs:tt $op:tt $rhs:tt >= $($rest:tt)*) => { $crate::__ensure_cmp!(@plain $orig) };
+    (@rhs $orig:tt $fuel:tt $lhs:tt $op:tt $rhs:tt < $($rest:tt)*) => { $crate::__ensure_cmp!(@plain $orig) };
+    (@rhs $orig:tt $fuel:tt $lhs:tt $op:tt $rhs:tt > $($rest:tt)*) => { $crate::__ensure_cmp!(@plain $orig) };
+    (@rhs $orig:tt $fuel:tt $lhs:tt $op:tt $rhs:tt && $($rest:tt)*) => { $crate::__ensure_cmp!(@plain $orig) };
+    (@rhs $orig:tt $fuel:tt $lhs:tt $op:tt $rhs:tt || $($rest:tt)*) => { $crate::__ensure_cmp!(@plain $orig) };
+    (@rhs $orig:tt $fuel:tt $lhs:tt $op:tt $rhs:tt = $($rest:tt)*) => { $crate::__ensure_cmp!(@plain $orig) };
+    (@rhs $orig:tt $fuel:tt $lhs:tt $op:tt $rhs:tt .. $($rest:tt)*) => { $crate::__ensure_cmp!(@plain $orig) };
+    (@rhs $orig:tt $fuel:tt $lhs:tt $op:tt $rhs:tt ..= $($rest:tt)*) => { $crate::__ensure_cmp!(@plain $orig) };
+    (@rhs $orig:tt $fuel:tt $lhs:tt $op:tt [$($rhs:tt)+] ,) => { $crate::__ensure_cmp!(@cmp $lhs $op [$($rhs)+]) };
+    (@rhs $orig:tt [~ $($fuel:tt)*] $lhs:tt $op:tt [$($rhs:tt)*] $next:tt $($rest:tt)*) => { $crate::__ensure_cmp!(@rhs $orig [$($fuel)*] $lhs $op [$($rhs)* $next] $($rest)*) };
+    (@rhs $orig:tt $fuel:tt $lhs:tt $op:tt [$($rhs:tt)+]) => { $crate::__ensure_cmp!(@cmp $lhs $op [$($rhs)+]) };
+
+    (@cmp [$($lhs:tt)+] [$op:tt] [$($rhs:tt)+]) => {
+        match (&($($lhs)+), &($($rhs)+)) {
+            (lhs, rhs) => {
+                if !(lhs $op rhs) {
+                    #[allow(unused_imports)]
+                    use $crate::__private::{RenderDebug as _, RenderNone as _};
+                    return ::std::result::Result::Err($crate::__private::ensure_failed(
+                        ::std::concat!("Condition failed: `", ::std::stringify!($($lhs)+ $op $($rhs)+), "`"),
+                        (&$crate::__private::DebugIfAble(lhs)).__regardless_debug(),
+                        (&$crate::__private::DebugIfAble(rhs)).__regardless_debug(),
+                    ));
+                }
+            }
+        }
+    };
+
+    (@plain [$($orig:tt)+]) => { $crate::__ensure_cmp!(@expr $($orig)+) };
+    (@expr $cond:expr $(,)?) => {
+        if !$cond {
+            return ::std::result::Result::Err($crate::Error::msg(::std::concat!(
+                "Condition failed: `",
+                ::std::stringify!($cond),
+                "`"
+            )))
+        }
+    };
+}
+
+#[doc(hidden)]
+pub mod __private {
+    pub use crate::ensure::{ensure_failed, DebugIfAble, RenderDebug, RenderNone};
+
+    pub mod kind {
+        pub use crate::kind::{AdhocKind, BoxedKind, TraitKind};
+    }
+
+    // A literal without arguments or inline captures is stored as a
+    // `&'static str` instead of being copied into a `String`.
+    #[track_caller]
+    pub fn format_err(args: std::fmt::Arguments<'_>) -> crate::Error {
+        match args.as_str() {
+            Some(message) => crate::Error::from_adhoc(message),
+            None => crate::Error::from_adhoc(std::fmt::format(args)),
+        }
+    }
+}
+
 pub type Result<T, E = Error> = std::result::Result<T, E>;
 
 pub struct Error {
-    inner: Box<dyn StdError + Send + Sync + 'static>,
-    context: Vec<String>,
+    inner: Own<ErrorImpl>,
 }
 
+// `Error` is a single thin pointer, and the pointer's niche keeps
+// `Option<Error>` and `Result<(), Error>` the same size.
+const _: () = {
+    use std::mem::size_of;
+    assert!(size_of::<Error>() == size_of::<usize>());
+    assert!(size_of::<Option<Error>>() == size_of::<usize>());
+    assert!(size_of::<Result<(), Error>>() == size_of::<usize>());
+};
+
 impl Error {
-    pub fn from_str(s: &str) -> Self {
-        Self {
-            inner: s.into(),
-            context: Vec::new(),
-        }
+    /// Create an error from a message, keeping the value itself so it can be
+    /// downcast to later.
+    #[track_caller]
+    pub fn msg<M>(message: M) -> Self
+    where
+        M: Display + Debug + Send + Sync + 'static,
+    {
+        Self::from_adhoc(message)
+    }
+
+    /// Wrap a std error. Equivalent to `Error::from`, for when inference needs
+    /// a hand.
+    #[track_caller]
+    pub fn new<E>(error: E) -> Self
+    where
+        E: StdError + Send + Sync + 'static,
+    {
+        Self::from_std(error)
     }
 
+    #[deprecated(note = "use `Error::msg` instead")]
+    #[track_caller]
     pub fn from_string(s: String) -> Self {
-        Self {
-            inner: s.into(),
-            context: Vec::new(),
-        }
+        Self::msg(s)
     }
-}
 
-impl Deref for Error {
-    type Target = dyn StdError + Send + Sync + 'static;
+    /// Iterate over every layer of this error, starting with the outermost
+    /// context and ending with the root cause.
+    pub fn chain(&self) -> Chain<'_> {
+        Chain::new(&**self)
+    }
 
-    fn deref(&self) -> &Self::Target {
-        self.inner.as_ref()
+    /// The innermost error in the chain.
+    pub fn root_cause(&self) -> &(dyn StdError + 'static) {
+        self.chain()
+            .last()
+            .expect("chain always contains the wrapped error")
     }
-}
 
-impl DerefMut for Error {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        self.inner.as_mut()
+    /// Returns true if [`downcast_ref`](Self::downcast_ref) finds a `T`, that is
+    /// if `downcast::<T>()` would succeed.
+    pub fn is<T>(&self) -> bool
+    where
+        T: Display + Send + Sync + 'static,
+    {
+        self.downcast_ref::<T>().is_some()
     }
-}
 
-impl Display for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(
-            f,
-            "{}{}",
-            self.inner,
-            self.context
-                .iter()
-                .fold(String::new(), |acc, c| acc + "\n" + c)
-        )
+    /// Returns true if `T` is anywhere in the chain, as with
+    /// [`find_source`](Self::find_source).
+    pub fn is_source<T>(&self) -> bool
+    where
+        T: StdError + Send + Sync + 'static,
+    {
+        self.find_source::<T>().is_some()
     }
 }
 
-impl Debug for Error {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        std::fmt::Display::fmt(self, f)
+impl FromStr for Error {
+    type Err = Infallible;
+
+    #[track_caller]
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(Self::msg(s.to_owned()))
     }
 }
 
-impl<E> From<E> for Error
-where
-    E: StdError + Send + Sync + 'static,
-{
-    fn from(value: E) -> Self {
-        Self {
-            inner: Box::new(value),
-            context: Vec::new(),
-        }
+impl Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.display(f)
     }
 }
 
-impl Error {
-    pub fn extend_context(&mut self, s: String) {
-        self.context.push(s)
+impl Debug for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.debug(f)
     }
 }
 
@@ -102,20 +293,18 @@ impl<T, E> Context<T, E> for Result<T, E>
 where
     E: StdError + Send + Sync + 'static,
 {
+    #[track_caller]
     fn context<C>(self, context: C) -> Result<T, Error>
     where
         C: Display + Send + Sync + 'static,
     {
         match self {
             Ok(ok) => Ok(ok),
-            Err(error) => Err({
-                let mut res = Error::from(error);
-                res.extend_context(context.to_string());
-                res
-            }),
+            Err(error) => Err(Error::from_context(context, error)),
         }
     }
 
+    #[track_caller]
     fn with_context<C, F>(self, context: F) -> Result<T, Error>
     where
         C: Display + Send + Sync + 'static,
@@ -123,16 +312,13 @@ where
     {
         match self {
             Ok(ok) => Ok(ok),
-            Err(error) => Err({
-                let mut res = Error::from(error);
-                res.extend_context(context().to_string());
-                res
-            }),
+            Err(error) => Err(Error::from_context(context(), error)),
         }
     }
 }
 
 impl<T> Context<T, Error> for Result<T, Error> {
+    #[track_caller]
     fn context<C>(self, context: C) -> Result<T, Error>
     where
         C: Display + Send + Sync + 'static,
@@ -141,12 +327,13 @@ impl<T> Context<T, Error> for Result<T, Error> {
             Ok(ok) => Ok(ok),
             Err(error) => Err({
                 let mut res = error;
-                res.extend_context(context.to_string());
+                res.extend_context(context);
                 res
             }),
         }
     }
 
+    #[track_caller]
     fn with_context<C, F>(self, context: F) -> Result<T, Error>
     where
         C: Display + Send + Sync + 'static,
@@ -156,16 +343,59 @@ impl<T> Context<T, Error> for Result<T, Error> {
             Ok(ok) => Ok(ok),
             Err(error) => Err({
                 let mut res = error;
-                res.extend_context(context().to_string());
+                res.extend_context(context());
                 res
             }),
         }
     }
 }
 
+impl<T> Context<T, Infallible> for Option<T> {
+    #[track_caller]
+    fn context<C>(self, context: C) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+    {
+        match self {
+            Some(ok) => Ok(ok),
+            None => Err(Error::from_display(context)),
+        }
+    }
+
+    #[track_caller]
+    fn with_context<C, F>(self, context: F) -> Result<T, Error>
+    where
+        C: Display + Send + Sync + 'static,
+        F: FnOnce() -> C,
+    {
+        match self {
+            Some(ok) => Ok(ok),
+            None => Err(Error::from_display(context())),
+        }
+    }
+}
+
+impl From<Error> for Box<dyn StdError + Send + Sync + 'static> {
+    fn from(error: Error) -> Self {
+        error.into_boxed_dyn_error()
+    }
+}
+
 impl From<Error> for Box<dyn StdError + Send + 'static> {
     fn from(error: Error) -> Self {
-        error.inner
+        error.into_boxed_dyn_error()
+    }
+}
+
+impl From<Error> for Box<dyn StdError + 'static> {
+    fn from(error: Error) -> Self {
+        error.into_boxed_dyn_error()
+    }
+}
+
+impl AsRef<dyn StdError> for Error {
+    fn as_ref(&self) -> &(dyn StdError + 'static) {
+        &**self
     }
 }
 
@@ -0,0 +1,191 @@
+use std::{
+    error::Error as StdError,
+    fmt::{self, Debug, Display},
+    slice, vec,
+};
+
+use crate::{fmt::NestedReports, remote::RemoteError, Error};
+
+/// Many errors reported as one, for jobs that keep going after a failure and
+/// want to report every one of them.
+///
+/// Turned into an [`Error`], it renders each child as an indented
+/// sub-report under "Errors:", up to [`with_limit`](Self::with_limit) of
+/// them followed by "and N more". Get it back with
+/// `error.downcast_ref::<MultiError>()` to inspect the children, each of which
+/// keeps its own `chain()`, context and attachments, or look for a value in
+/// the children with [`Error::find_in_children`]. [`Error::downcast_ref`] and
+/// [`Error::is`] do not look inside the children, since the aggregate is not
+/// any one of them.
+pub struct MultiError {
+    errors: Vec<Error>,
+    limit: usize,
+}
+
+impl MultiError {
+    pub fn new() -> Self {
+        MultiError {
+            errors: Vec::new(),
+            limit: 10,
+        }
+    }
+
+    /// Render at most `limit` children in the report. Defaults to 10.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    #[track_caller]
+    pub fn push<E>(&mut self, error: E)
+    where
+        E: Into<Error>,
+    {
+        self.errors.push(error.into());
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.errors.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.errors.len()
+    }
+
+    pub fn iter(&self) -> slice::Iter<'_, Error> {
+        self.errors.iter()
+    }
+
+    /// The first `T` that [`Error::downcast_ref`] finds in a child, in the order
+    /// they were pushed, also looking inside children that are `MultiError`s
+    /// themselves.
+    pub fn find<T>(&self) -> Option<&T>
+    where
+        T: Display + Send + Sync + 'static,
+    {
+        self.errors.iter().find_map(|error| {
+            error
+                .downcast_ref::<T>()
+                .or_else(|| error.find_in_children::<T>())
+        })
+    }
+
+    pub fn into_errors(self) -> Vec<Error> {
+        self.errors
+    }
+
+    /// `Ok(())` if nothing was pushed, and otherwise every error as one.
+    #[track_caller]
+    pub fn into_result(self) -> Result<(), Error> {
+        if self.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::from(self))
+        }
+    }
+
+    pub(crate) fn limit(&self) -> usize {
+        self.limit
+    }
+
+    // The children held by one entry of a chain, whether it is a `MultiError`
+    // or one rebuilt from another process.
+    pub(crate) fn of<'a>(cause: &'a (dyn StdError + 'static)) -> Option<&'a MultiError> {
+        cause
+            .downcast_ref::<MultiError>()
+            .or_else(|| cause.downcast_ref::<RemoteError>()?.errors())
+    }
+}
+
+impl Error {
+    // The first entry of the chain that holds children.
+    pub(crate) fn multi(&self) -> Option<&MultiError> {
+        self.chain().find_map(MultiError::of)
+    }
+}
+
+impl Default for MultiError {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Display for MultiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.errors.len() {
+            1 => f.write_str("1 error occurred"),
+            n => write!(f, "{} errors occurred", n),
+        }
+    }
+}
+
+impl Debug for MultiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(&self.errors).finish()
+    }
+}
+
+// The children are siblings rather than causes, so there is no `source()`.
+impl StdError for MultiError {}
+
+impl NestedReports for MultiError {
+    fn heading(&self) -> &'static str {
+        "Errors:"
+    }
+
+    fn reports(&self) -> Vec<(usize, &Error)> {
+        self.iter().take(self.limit()).enumerate().collect()
+    }
+
+    fn hidden(&self) -> usize {
+        self.len().saturating_sub(self.limit())
+    }
+}
+
+impl<E> Extend<E> for MultiError
+where
+    E: Into<Error>,
+{
+    #[track_caller]
+    fn extend<I>(&mut self, iter: I)
+    where
+        I: IntoIterator<Item = E>,
+    {
+        for error in iter {
+            self.push(error);
+        }
+    }
+}
+
+impl<E> FromIterator<E> for MultiError
+where
+    E: Into<Error>,
+{
+    #[track_caller]
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = E>,
+    {
+        let mut multi = MultiError::new();
+        multi.extend(iter);
+        multi
+    }
+}
+
+impl IntoIterator for MultiError {
+    type Item = Error;
+    type IntoIter = vec::IntoIter<Error>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a MultiError {
+    type Item = &'a Error;
+    type IntoIter = slice::Iter<'a, Error>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.errors.iter()
+    }
+}
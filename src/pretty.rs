@@ -0,0 +1,140 @@
+use std::{
+    env,
+    ffi::OsStr,
+    fmt,
+    io::{self, IsTerminal},
+};
+
+use crate::{
+    fmt::{Style, Theme},
+    hook::{set_hook, InstallError, ReportHandler},
+    Error,
+};
+
+/// Whether [`PrettyHandler`] should emit ANSI colours.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Colour only when stderr is a terminal and `NO_COLOR` is unset or empty.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// A [`ReportHandler`] that renders the same report as [`DefaultHandler`](crate::DefaultHandler),
+/// with the headline, cause numbers and backtrace frames coloured.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct PrettyHandler {
+    color: ColorChoice,
+}
+
+impl PrettyHandler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn color(mut self, color: ColorChoice) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// Install a hook that renders every error created from now on with a
+    /// `PrettyHandler::new()`.
+    pub fn install() -> Result<(), InstallError> {
+        set_hook(Box::new(|_| Box::new(PrettyHandler::new())))
+    }
+
+    fn use_color(&self) -> bool {
+        self.color.enabled(
+            env::var_os("NO_COLOR").as_deref(),
+            io::stderr().is_terminal(),
+        )
+    }
+}
+
+impl ColorChoice {
+    // Takes the value of `NO_COLOR` and whether stderr is a terminal rather
+    // than probing them, so the decision can be tested.
+    fn enabled(self, no_color: Option<&OsStr>, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => no_color.is_none_or(OsStr::is_empty) && is_terminal,
+        }
+    }
+}
+
+const COLOR: Theme = Theme {
+    headline: Style("1;31"),
+    heading: Style("1"),
+    number: Style("35"),
+    cause: Style("33"),
+    note: Style("1;36"),
+    suggestion: Style("1;32"),
+    warning: Style("1;33"),
+    frame: Style("32"),
+    location: Style("2"),
+};
+
+impl ReportHandler for PrettyHandler {
+    fn debug(&self, error: &Error, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if f.alternate() {
+            return fmt::Debug::fmt(&**error, f);
+        }
+
+        let theme = if self.use_color() {
+            &COLOR
+        } else {
+            &Theme::PLAIN
+        };
+        error.report(f, theme)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ffi::OsStr, fmt};
+
+    use super::{ColorChoice, PrettyHandler};
+    use crate::{hook::ReportHandler, Error};
+
+    struct Render<'a>(PrettyHandler, &'a Error);
+
+    impl fmt::Debug for Render<'_> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.debug(self.1, f)
+        }
+    }
+
+    #[test]
+    fn always_colors_and_never_matches_the_default_report() {
+        let error = Error::msg("root").context("outer");
+
+        let colored = format!(
+            "{:?}",
+            Render(PrettyHandler::new().color(ColorChoice::Always), &error)
+        );
+        assert!(colored.starts_with("\x1b[1;31mouter\x1b[0m"));
+        assert!(colored.contains("\x1b[1mCaused by:\x1b[0m"));
+        assert!(colored.contains("\x1b[33mroot\x1b[0m"));
+
+        let plain = format!(
+            "{:?}",
+            Render(PrettyHandler::new().color(ColorChoice::Never), &error)
+        );
+        assert!(!plain.contains('\x1b'));
+        assert_eq!(plain, format!("{:?}", error));
+    }
+
+    #[test]
+    fn auto_needs_a_terminal_and_no_no_color() {
+        let auto = ColorChoice::Auto;
+        assert!(auto.enabled(None, true));
+        assert!(auto.enabled(Some(OsStr::new("")), true));
+        assert!(!auto.enabled(Some(OsStr::new("1")), true));
+        assert!(!auto.enabled(None, false));
+
+        assert!(ColorChoice::Always.enabled(Some(OsStr::new("1")), false));
+        assert!(!ColorChoice::Never.enabled(None, true));
+    }
+}
@@ -0,0 +1,138 @@
+use std::{marker::PhantomData, ptr::NonNull};
+
+// Owned, type-erased pointer to a heap allocation made by `Box`.
+#[repr(transparent)]
+pub(crate) struct Own<T>
+where
+    T: ?Sized,
+{
+    pub(crate) ptr: NonNull<T>,
+}
+
+unsafe impl<T> Send for Own<T> where T: ?Sized {}
+
+unsafe impl<T> Sync for Own<T> where T: ?Sized {}
+
+impl<T> Copy for Own<T> where T: ?Sized {}
+
+impl<T> Clone for Own<T>
+where
+    T: ?Sized,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Own<T>
+where
+    T: ?Sized,
+{
+    pub(crate) fn new(ptr: Box<T>) -> Self {
+        Own {
+            // SAFETY: Box::into_raw never returns null.
+            ptr: unsafe { NonNull::new_unchecked(Box::into_raw(ptr)) },
+        }
+    }
+
+    pub(crate) fn cast<U>(self) -> Own<U> {
+        Own {
+            ptr: self.ptr.cast(),
+        }
+    }
+
+    pub(crate) unsafe fn boxed(self) -> Box<T> {
+        Box::from_raw(self.ptr.as_ptr())
+    }
+
+    pub(crate) fn by_ref(&self) -> Ref<'_, T> {
+        Ref {
+            ptr: self.ptr,
+            lifetime: PhantomData,
+        }
+    }
+
+    pub(crate) fn by_mut(&mut self) -> Mut<'_, T> {
+        Mut {
+            ptr: self.ptr,
+            lifetime: PhantomData,
+        }
+    }
+}
+
+// Shared, type-erased pointer that borrows for `'a`.
+#[repr(transparent)]
+pub(crate) struct Ref<'a, T>
+where
+    T: ?Sized,
+{
+    pub(crate) ptr: NonNull<T>,
+    lifetime: PhantomData<&'a T>,
+}
+
+impl<T> Copy for Ref<'_, T> where T: ?Sized {}
+
+impl<T> Clone for Ref<'_, T>
+where
+    T: ?Sized,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<'a, T> Ref<'a, T>
+where
+    T: ?Sized,
+{
+    pub(crate) fn new(ptr: &'a T) -> Self {
+        Ref {
+            ptr: NonNull::from(ptr),
+            lifetime: PhantomData,
+        }
+    }
+
+    pub(crate) fn cast<U>(self) -> Ref<'a, U> {
+        Ref {
+            ptr: self.ptr.cast(),
+            lifetime: PhantomData,
+        }
+    }
+
+    pub(crate) unsafe fn deref(self) -> &'a T {
+        &*self.ptr.as_ptr()
+    }
+}
+
+// Unique, type-erased pointer that borrows for `'a`.
+#[repr(transparent)]
+pub(crate) struct Mut<'a, T>
+where
+    T: ?Sized,
+{
+    pub(crate) ptr: NonNull<T>,
+    lifetime: PhantomData<&'a mut T>,
+}
+
+impl<'a, T> Mut<'a, T>
+where
+    T: ?Sized,
+{
+    pub(crate) fn new(ptr: &'a mut T) -> Self {
+        Mut {
+            ptr: NonNull::from(ptr),
+            lifetime: PhantomData,
+        }
+    }
+
+    pub(crate) fn cast<U>(self) -> Mut<'a, U> {
+        Mut {
+            ptr: self.ptr.cast(),
+            lifetime: PhantomData,
+        }
+    }
+
+    pub(crate) unsafe fn deref_mut(self) -> &'a mut T {
+        &mut *self.ptr.as_ptr()
+    }
+}
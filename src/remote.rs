@@ -0,0 +1,454 @@
+use std::{
+    error::Error as StdError,
+    fmt::{self, Debug, Display},
+};
+
+use crate::{
+    attachment::Attachment,
+    error::{Link, LinkKind, SourceLocation},
+    json::{self, FromJsonError, Value, JSON_VERSION},
+    multi::MultiError,
+    section::Heading,
+    Error,
+};
+
+/// One entry of the chain of an error that was serialized with
+/// [`Error::to_json`] in another process.
+///
+/// Each node knows whether it was a context value, the wrapped error or one
+/// of its sources, along with its type name and location on the other side,
+/// and the report renders it exactly as the original would have been.
+pub struct RemoteError {
+    message: String,
+    kind: LinkKind,
+    type_name: Option<String>,
+    location: Option<RemoteLocation>,
+    source: Option<Box<RemoteError>>,
+    // The children, if this node was a `MultiError`.
+    errors: Option<MultiError>,
+    // Only set on the outermost node.
+    backtrace: Option<RemoteBacktrace>,
+}
+
+/// Where a [`RemoteError`] node or backtrace frame was created.
+#[derive(Debug, Clone)]
+pub struct RemoteLocation {
+    file: String,
+    line: Option<u32>,
+    column: Option<u32>,
+}
+
+/// An attachment of a remote error. Only its type name and, if it was
+/// printable, its `Display` output survive the trip.
+#[derive(Debug, Clone)]
+pub struct RemoteAttachment {
+    type_name: String,
+    display: Option<String>,
+}
+
+#[derive(Debug)]
+pub(crate) struct RemoteBacktrace {
+    frames: Vec<RemoteFrame>,
+}
+
+#[derive(Debug)]
+struct RemoteFrame {
+    index: u32,
+    symbol: String,
+    location: Option<RemoteLocation>,
+}
+
+impl Error {
+    /// Rebuild an error from a report written by [`Error::to_json`].
+    ///
+    /// Every entry of the original chain becomes a [`RemoteError`] node, and
+    /// attachments, notes and sections are added back, so the result renders
+    /// and iterates like the original. The children of a `MultiError` are
+    /// rebuilt the same way and can be searched with
+    /// [`find_in_children`](Error::find_in_children). A remote backtrace takes
+    /// the place of the local one in the report.
+    ///
+    /// Reports of any version up to [`JSON_VERSION`] are
+    /// accepted; a report from before version 2 simply has no children.
+    #[track_caller]
+    pub fn from_json(json: &str) -> Result<Error, FromJsonError> {
+        Error::from_report(&json::parse(json)?)
+    }
+
+    #[track_caller]
+    fn from_report(report: &Value) -> Result<Error, FromJsonError> {
+        match report.get("version").map(|version| version.as_u32()) {
+            Some(Some(1..=JSON_VERSION)) => {}
+            Some(version) => return Err(FromJsonError::version(version)),
+            None => return Err(FromJsonError::version(None)),
+        }
+
+        let chain = report
+            .get("chain")
+            .and_then(Value::as_array)
+            .filter(|chain| !chain.is_empty())
+            .ok_or(FromJsonError::field("chain"))?;
+        let mut source = None;
+        for entry in chain.iter().rev() {
+            source = Some(Box::new(RemoteError::from_entry(entry, source)?));
+        }
+        let mut root = source.expect("chain is not empty");
+
+        let backtrace = report
+            .get("backtrace")
+            .ok_or(FromJsonError::field("backtrace"))?;
+        root.backtrace = RemoteBacktrace::from_value(backtrace)?;
+
+        let mut error = Error::from_remote(*root);
+
+        let attachments = array(report, "attachments")?;
+        for attachment in attachments {
+            let attachment = RemoteAttachment {
+                type_name: string(attachment, "type")?.to_owned(),
+                display: optional_string(attachment, "display")?.map(str::to_owned),
+            };
+            error = error.with_attachment(Attachment::remote(attachment));
+        }
+
+        let sections = array(report, "sections")?;
+        for section in sections {
+            let heading = match string(section, "title")? {
+                "Note" => Heading::Note,
+                "Suggestion" => Heading::Suggestion,
+                "Warning" => Heading::Warning,
+                title => Heading::Custom(Box::new(title.to_owned())),
+            };
+            let body = string(section, "body")?.to_owned();
+            error = error.with_section(heading, Box::new(body));
+        }
+
+        Ok(error)
+    }
+
+    // The backtrace of the outermost remote error in the chain, if it had one.
+    pub(crate) fn remote_backtrace(&self) -> Option<&RemoteBacktrace> {
+        self.chain()
+            .find_map(|cause| cause.downcast_ref::<RemoteError>())?
+            .backtrace
+            .as_ref()
+    }
+}
+
+impl RemoteError {
+    fn from_entry(entry: &Value, source: Option<Box<RemoteError>>) -> Result<Self, FromJsonError> {
+        let kind = match string(entry, "kind")? {
+            "context" => LinkKind::Context,
+            "error" => LinkKind::Error,
+            "source" => LinkKind::Source,
+            _ => return Err(FromJsonError::field("kind")),
+        };
+        let errors = match entry.get("errors") {
+            Some(errors) => {
+                let limit = entry
+                    .get("limit")
+                    .and_then(Value::as_u32)
+                    .ok_or(FromJsonError::field("limit"))?;
+                let errors = errors
+                    .as_array()
+                    .ok_or(FromJsonError::field("errors"))?
+                    .iter()
+                    .map(Error::from_report)
+                    .collect::<Result<MultiError, FromJsonError>>()?;
+                Some(errors.with_limit(limit as usize))
+            }
+            None => None,
+        };
+        Ok(RemoteError {
+            message: string(entry, "message")?.to_owned(),
+            kind,
+            type_name: optional_string(entry, "type")?.map(str::to_owned),
+            location: RemoteLocation::from_value(entry.get("location"))?,
+            source,
+            errors,
+            backtrace: None,
+        })
+    }
+
+    /// `"context"`, `"error"` or `"source"`, as in the serialized report.
+    pub fn kind(&self) -> &str {
+        match self.kind {
+            LinkKind::Context => "context",
+            LinkKind::Error => "error",
+            LinkKind::Source => "source",
+        }
+    }
+
+    /// The type name of the value on the other side, if it was known there.
+    pub fn type_name(&self) -> Option<&str> {
+        self.type_name.as_deref()
+    }
+
+    pub fn location(&self) -> Option<&RemoteLocation> {
+        self.location.as_ref()
+    }
+
+    pub(crate) fn errors(&self) -> Option<&MultiError> {
+        self.errors.as_ref()
+    }
+
+    pub(crate) fn link(&self) -> Link<'_> {
+        Link {
+            kind: self.kind,
+            type_name: self.type_name(),
+            location: self.location.as_ref().map(RemoteLocation::as_source),
+        }
+    }
+}
+
+// A chain can be arbitrarily long, so neither `Debug` nor `Drop` may recurse
+// into `source`.
+impl Debug for RemoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RemoteError")
+            .field("message", &self.message)
+            .field("kind", &self.kind)
+            .field("type_name", &self.type_name)
+            .field("location", &self.location)
+            .field(
+                "source",
+                &self.source.as_ref().map(|source| &source.message),
+            )
+            .field("errors", &self.errors)
+            .field("backtrace", &self.backtrace)
+            .finish()
+    }
+}
+
+impl Drop for RemoteError {
+    fn drop(&mut self) {
+        let mut next = self.source.take();
+        while let Some(mut node) = next {
+            next = node.source.take();
+        }
+    }
+}
+
+impl Display for RemoteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl StdError for RemoteError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.source
+            .as_deref()
+            .map(|source| source as &(dyn StdError + 'static))
+    }
+}
+
+impl RemoteLocation {
+    fn from_value(value: Option<&Value>) -> Result<Option<Self>, FromJsonError> {
+        let value = match value {
+            Some(value) if !value.is_null() => value,
+            _ => return Ok(None),
+        };
+        Ok(Some(RemoteLocation {
+            file: string(value, "file")?.to_owned(),
+            line: optional_u32(value, "line")?,
+            column: optional_u32(value, "column")?,
+        }))
+    }
+
+    pub fn file(&self) -> &str {
+        &self.file
+    }
+
+    pub fn line(&self) -> Option<u32> {
+        self.line
+    }
+
+    pub fn column(&self) -> Option<u32> {
+        self.column
+    }
+
+    fn as_source(&self) -> SourceLocation<'_> {
+        SourceLocation {
+            file: &self.file,
+            line: self.line,
+            column: self.column,
+        }
+    }
+}
+
+impl Display for RemoteLocation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.as_source(), f)
+    }
+}
+
+impl RemoteAttachment {
+    pub fn type_name(&self) -> &str {
+        &self.type_name
+    }
+
+    /// The attachment's `Display` output, if it was attached as printable.
+    pub fn display(&self) -> Option<&str> {
+        self.display.as_deref()
+    }
+}
+
+impl Display for RemoteAttachment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.display.as_deref().unwrap_or_default())
+    }
+}
+
+impl RemoteBacktrace {
+    fn from_value(value: &Value) -> Result<Option<Self>, FromJsonError> {
+        if string(value, "status")? != "captured" {
+            return Ok(None);
+        }
+        let frames = array(value, "frames")?
+            .iter()
+            .map(|frame| {
+                Ok(RemoteFrame {
+                    index: frame
+                        .get("index")
+                        .and_then(Value::as_u32)
+                        .ok_or(FromJsonError::field("index"))?,
+                    symbol: string(frame, "symbol")?.to_owned(),
+                    location: RemoteLocation::from_value(frame.get("location"))?,
+                })
+            })
+            .collect::<Result<_, FromJsonError>>()?;
+        Ok(Some(RemoteBacktrace { frames }))
+    }
+}
+
+// The same layout std uses, so it renders and serializes like a local one.
+impl Display for RemoteBacktrace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for frame in &self.frames {
+            writeln!(f, "{: >4}: {}", frame.index, frame.symbol)?;
+            if let Some(location) = &frame.location {
+                writeln!(f, "             at {}", location)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn string<'a>(value: &'a Value, field: &'static str) -> Result<&'a str, FromJsonError> {
+    value
+        .get(field)
+        .and_then(Value::as_str)
+        .ok_or(FromJsonError::field(field))
+}
+
+fn optional_string<'a>(
+    value: &'a Value,
+    field: &'static str,
+) -> Result<Option<&'a str>, FromJsonError> {
+    match value.get(field) {
+        Some(Value::Null) => Ok(None),
+        Some(Value::String(s)) => Ok(Some(s)),
+        _ => Err(FromJsonError::field(field)),
+    }
+}
+
+fn optional_u32(value: &Value, field: &'static str) -> Result<Option<u32>, FromJsonError> {
+    match value.get(field) {
+        Some(Value::Null) => Ok(None),
+        Some(number) => number.as_u32().map(Some).ok_or(FromJsonError::field(field)),
+        None => Err(FromJsonError::field(field)),
+    }
+}
+
+fn array<'a>(value: &'a Value, field: &'static str) -> Result<&'a [Value], FromJsonError> {
+    value
+        .get(field)
+        .and_then(Value::as_array)
+        .ok_or(FromJsonError::field(field))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use crate::{Error, MultiError};
+
+    #[test]
+    fn deeply_nested_input_is_rejected() {
+        let json = "[".repeat(200_000);
+        assert!(Error::from_json(&json).is_err());
+    }
+
+    #[test]
+    fn long_chain_round_trips_and_drops() {
+        let entry = r#"{"kind":"source","message":"cause","type":null,"location":null}"#;
+        let chain = vec![entry; 300_000].join(",");
+        let json = format!(
+            r#"{{"version":{},"message":"cause","chain":[{}],"attachments":[],"sections":[],"backtrace":{{"status":"disabled","frames":[]}}}}"#,
+            crate::JSON_VERSION,
+            chain,
+        );
+        let error = Error::from_json(&json).unwrap();
+        assert_eq!(error.chain().count(), 300_000);
+        assert!(format!(
+            "{:?}",
+            error.root_cause().downcast_ref::<super::RemoteError>()
+        )
+        .contains("cause"));
+        drop(error);
+    }
+
+    #[test]
+    fn remote_error_has_no_local_backtrace() {
+        let json = format!(
+            r#"{{"version":{},"message":"m","chain":[{{"kind":"error","message":"m","type":null,"location":null}}],"attachments":[],"sections":[],"backtrace":{{"status":"disabled","frames":[]}}}}"#,
+            crate::JSON_VERSION,
+        );
+        let error = Error::from_json(&json).unwrap();
+        assert!(error
+            .to_json()
+            .contains(r#""backtrace":{"status":"disabled","frames":[]}"#));
+    }
+
+    #[test]
+    fn older_versions_are_read_and_newer_ones_rejected() {
+        let report = |version: u32| {
+            format!(
+                r#"{{"version":{},"message":"m","chain":[{{"kind":"error","message":"m","type":null,"location":null}}],"attachments":[],"sections":[],"backtrace":{{"status":"disabled","frames":[]}}}}"#,
+                version,
+            )
+        };
+
+        let error = Error::from_json(&report(1)).unwrap();
+        assert_eq!(error.to_string(), "m");
+        assert!(error.multi().is_none());
+
+        let newer = crate::JSON_VERSION + 1;
+        let rejected = Error::from_json(&report(newer)).unwrap_err();
+        assert_eq!(
+            rejected.to_string(),
+            format!(
+                "unsupported error report version {}, expected at most {}",
+                newer,
+                crate::JSON_VERSION
+            )
+        );
+        assert!(Error::from_json(&report(0)).is_err());
+    }
+
+    #[test]
+    fn multi_error_children_round_trip() {
+        let mut multi = MultiError::new().with_limit(1);
+        multi.push(Error::msg("first").context("while reading"));
+        multi.push(io::Error::from(io::ErrorKind::NotFound));
+        let error = Error::from(multi).context("batch");
+
+        let back = Error::from_json(&error.to_json()).unwrap();
+        assert_eq!(format!("{:?}", back), format!("{:?}", error));
+        assert_eq!(back.to_json(), error.to_json());
+        let children = back.multi().expect("children survive");
+        assert_eq!(children.len(), 2);
+        assert_eq!(children.limit(), 1);
+        assert!(back.is::<super::RemoteError>());
+    }
+}
@@ -0,0 +1,93 @@
+use std::process::{ExitCode, Termination};
+
+use crate::Error;
+
+/// The outcome of `main`, for binaries that want a full error report. Return
+/// it from `main` with `run().into()`.
+///
+/// On failure the report is written to stderr as `Error: {:?}`, and the
+/// process exits with the code attached through [`Error::with_exit_code`], or 1
+/// if there is none or it is 0.
+pub struct Report(Result<(), Error>);
+
+impl Report {
+    pub fn new(result: Result<(), Error>) -> Self {
+        Self(result)
+    }
+
+    pub fn exit_code(&self) -> ExitCode {
+        match &self.0 {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(error) => error
+                .exit_code()
+                .filter(|&code| code != 0)
+                .map_or(ExitCode::FAILURE, ExitCode::from),
+        }
+    }
+}
+
+impl<E> From<Result<(), E>> for Report
+where
+    E: Into<Error>,
+{
+    #[track_caller]
+    fn from(result: Result<(), E>) -> Self {
+        match result {
+            Ok(()) => Self(Ok(())),
+            Err(error) => Self(Err(error.into())),
+        }
+    }
+}
+
+impl From<Error> for Report {
+    fn from(error: Error) -> Self {
+        Self(Err(error))
+    }
+}
+
+impl Termination for Report {
+    fn report(self) -> ExitCode {
+        let code = self.exit_code();
+        if let Err(error) = self.0 {
+            eprintln!("Error: {:?}", error);
+        }
+        code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::process::ExitCode;
+
+    use super::Report;
+    use crate::Error;
+
+    #[test]
+    fn success_exits_with_zero() {
+        assert_eq!(Report::new(Ok(())).exit_code(), ExitCode::SUCCESS);
+    }
+
+    #[test]
+    fn error_without_a_code_exits_with_one() {
+        let report = Report::from(Error::msg("failed").context("outer"));
+        assert_eq!(report.exit_code(), ExitCode::FAILURE);
+    }
+
+    #[test]
+    fn attached_code_is_used_through_context_layers() {
+        let error = Error::msg("failed").with_exit_code(3).context("outer");
+        assert_eq!(Report::from(error).exit_code(), ExitCode::from(3));
+
+        let error = Error::msg("failed")
+            .with_exit_code(3)
+            .context("outer")
+            .with_exit_code(4);
+        assert_eq!(Report::from(error).exit_code(), ExitCode::from(4));
+    }
+
+    #[test]
+    fn zero_does_not_exit_successfully() {
+        let error = Error::msg("failed").with_exit_code(0);
+        assert_eq!(Report::from(error).exit_code(), ExitCode::FAILURE);
+    }
+}
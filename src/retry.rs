@@ -0,0 +1,484 @@
+use std::{
+    error::Error as StdError,
+    fmt::{self, Display},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::{fmt::NestedReports, Error};
+
+/// Marks an error as worth retrying. Attach it with [`Error::transient`] or
+/// `error.attach(Transient)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Transient;
+
+impl Error {
+    /// Mark this error as [`Transient`], so [`retry`] tries again.
+    pub fn transient(self) -> Self {
+        self.attach(Transient)
+    }
+}
+
+/// Where [`retry`] gets the time from and how it waits between attempts.
+/// Swap it out to run retries in tests without sleeping.
+pub trait Clock {
+    fn now(&self) -> Instant;
+    fn sleep(&self, duration: Duration);
+}
+
+/// The real clock and `std::thread::sleep`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        thread::sleep(duration);
+    }
+}
+
+/// How often and how patiently [`retry`] tries again.
+///
+/// By default an error is retried only when it carries a [`Transient`]
+/// attachment; [`retry_if`](Self::retry_if) adds a predicate that is asked
+/// about every entry of the error's `chain()`.
+pub struct RetryPolicy {
+    backoff: Backoff,
+    max_attempts: u32,
+    max_elapsed: Option<Duration>,
+    predicate: Option<Predicate>,
+    clock: Box<dyn Clock>,
+}
+
+type Predicate = Box<dyn Fn(&(dyn StdError + 'static)) -> bool>;
+
+enum Backoff {
+    Fixed(Duration),
+    Exponential {
+        initial: Duration,
+        factor: u32,
+        max: Option<Duration>,
+    },
+}
+
+impl RetryPolicy {
+    /// Wait `delay` between attempts, for at most 3 attempts.
+    pub fn fixed(delay: Duration) -> Self {
+        Self::with_backoff(Backoff::Fixed(delay))
+    }
+
+    /// Wait `initial`, then twice as long after every further failure, for at
+    /// most 3 attempts.
+    pub fn exponential(initial: Duration) -> Self {
+        Self::with_backoff(Backoff::Exponential {
+            initial,
+            factor: 2,
+            max: None,
+        })
+    }
+
+    fn with_backoff(backoff: Backoff) -> Self {
+        RetryPolicy {
+            backoff,
+            max_attempts: 3,
+            max_elapsed: None,
+            predicate: None,
+            clock: Box::new(SystemClock),
+        }
+    }
+
+    /// Stop after `attempts` attempts in total, including the first.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Give up rather than wait past `elapsed` since the first attempt.
+    pub fn max_elapsed(mut self, elapsed: Duration) -> Self {
+        self.max_elapsed = Some(elapsed);
+        self
+    }
+
+    /// Grow an exponential backoff by `factor` instead of 2. Has no effect on
+    /// a fixed one.
+    pub fn factor(mut self, factor: u32) -> Self {
+        if let Backoff::Exponential { factor: f, .. } = &mut self.backoff {
+            *f = factor;
+        }
+        self
+    }
+
+    /// Never wait longer than `max` between attempts.
+    pub fn max_delay(mut self, max: Duration) -> Self {
+        match &mut self.backoff {
+            Backoff::Fixed(delay) => *delay = (*delay).min(max),
+            Backoff::Exponential { max: m, .. } => *m = Some(max),
+        }
+        self
+    }
+
+    /// Also retry when `predicate` holds for any entry of the error's
+    /// `chain()`.
+    pub fn retry_if<P>(mut self, predicate: P) -> Self
+    where
+        P: Fn(&(dyn StdError + 'static)) -> bool + 'static,
+    {
+        self.predicate = Some(Box::new(predicate));
+        self
+    }
+
+    pub fn clock<C>(mut self, clock: C) -> Self
+    where
+        C: Clock + 'static,
+    {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    fn is_transient(&self, error: &Error) -> bool {
+        error.request_ref::<Transient>().is_some()
+            || self
+                .predicate
+                .as_ref()
+                .is_some_and(|predicate| error.chain().any(predicate))
+    }
+
+    // The wait after the `attempt`th failure, counting from 1.
+    fn delay(&self, attempt: u32) -> Duration {
+        match self.backoff {
+            Backoff::Fixed(delay) => delay,
+            Backoff::Exponential {
+                initial,
+                factor,
+                max,
+            } => {
+                let delay = factor
+                    .checked_pow(attempt - 1)
+                    .and_then(|scale| initial.checked_mul(scale))
+                    .unwrap_or(Duration::MAX);
+                max.map_or(delay, |max| delay.min(max))
+            }
+        }
+    }
+}
+
+/// Call `op` until it succeeds, fails with an error that is not transient, or
+/// `policy` runs out of attempts or time.
+///
+/// The error returned is the last attempt's, with an [`Attempts`] context
+/// layer that holds the errors of all the earlier attempts. The report lists
+/// them under "Previous attempts:".
+#[track_caller]
+pub fn retry<T, F>(policy: RetryPolicy, mut op: F) -> Result<T, Error>
+where
+    F: FnMut() -> Result<T, Error>,
+{
+    let start = policy.clock.now();
+    let mut previous = Vec::new();
+    let mut attempt = 1;
+    loop {
+        let error = match op() {
+            Ok(ok) => return Ok(ok),
+            Err(error) => error,
+        };
+
+        let outcome = if !policy.is_transient(&error) {
+            Outcome::Permanent
+        } else if attempt >= policy.max_attempts {
+            Outcome::Exhausted
+        } else {
+            let delay = policy.delay(attempt);
+            let elapsed = policy.clock.now().saturating_duration_since(start);
+            match policy.max_elapsed {
+                Some(max) if elapsed.saturating_add(delay) > max => Outcome::OutOfTime,
+                _ => {
+                    policy.clock.sleep(delay);
+                    previous.push(error);
+                    attempt += 1;
+                    continue;
+                }
+            }
+        };
+
+        return Err(error.context_with_reports(Attempts { previous, outcome }));
+    }
+}
+
+/// Context added by [`retry`] to the error of the last attempt.
+pub struct Attempts {
+    previous: Vec<Error>,
+    outcome: Outcome,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Permanent,
+    Exhausted,
+    OutOfTime,
+}
+
+impl Attempts {
+    /// How many attempts were made, including the last.
+    pub fn count(&self) -> usize {
+        self.previous.len() + 1
+    }
+
+    /// The errors of every attempt before the last, oldest first.
+    pub fn previous(&self) -> &[Error] {
+        &self.previous
+    }
+
+    /// True if the last error was not transient, rather than retries running
+    /// out.
+    pub fn is_permanent(&self) -> bool {
+        self.outcome == Outcome::Permanent
+    }
+}
+
+impl Display for Attempts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let count = self.count();
+        let plural = if count == 1 { "" } else { "s" };
+        match self.outcome {
+            Outcome::Permanent => write!(f, "permanent error after {} attempt{}", count, plural),
+            Outcome::Exhausted => write!(f, "gave up after {} attempt{}", count, plural),
+            Outcome::OutOfTime => write!(f, "ran out of time after {} attempt{}", count, plural),
+        }
+    }
+}
+
+impl NestedReports for Attempts {
+    fn heading(&self) -> &'static str {
+        "Previous attempts:"
+    }
+
+    fn reports(&self) -> Vec<(usize, &Error)> {
+        (1..).zip(&self.previous).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        cell::{Cell, RefCell},
+        io,
+        rc::Rc,
+        time::{Duration, Instant},
+    };
+
+    use super::{retry, Attempts, Clock, RetryPolicy};
+    use crate::{Error, MultiError};
+
+    // Records every requested sleep and moves its time forward by it instead.
+    #[derive(Clone)]
+    struct FakeClock {
+        start: Instant,
+        elapsed: Rc<Cell<Duration>>,
+        sleeps: Rc<RefCell<Vec<Duration>>>,
+    }
+
+    impl FakeClock {
+        fn new() -> Self {
+            FakeClock {
+                start: Instant::now(),
+                elapsed: Rc::default(),
+                sleeps: Rc::default(),
+            }
+        }
+
+        fn sleeps(&self) -> Vec<Duration> {
+            self.sleeps.borrow().clone()
+        }
+    }
+
+    impl Clock for FakeClock {
+        fn now(&self) -> Instant {
+            self.start + self.elapsed.get()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.elapsed.set(self.elapsed.get() + duration);
+            self.sleeps.borrow_mut().push(duration);
+        }
+    }
+
+    // Run `policy` against an operation that always fails with a transient
+    // error, returning the final error and the sleeps in between.
+    fn always_failing(policy: RetryPolicy) -> (Error, Vec<Duration>) {
+        let clock = FakeClock::new();
+        let error = retry(policy.clock(clock.clone()), || -> Result<(), Error> {
+            Err(Error::msg("flaky").transient())
+        })
+        .unwrap_err();
+        (error, clock.sleeps())
+    }
+
+    fn attempts(error: &Error) -> &Attempts {
+        error.downcast_ref::<Attempts>().unwrap()
+    }
+
+    const MS: Duration = Duration::from_millis(1);
+
+    #[test]
+    fn earlier_attempts_are_rendered_in_the_report() {
+        let mut count = 0;
+        let policy = RetryPolicy::fixed(Duration::ZERO).max_attempts(3);
+        let error = retry(policy, || -> Result<(), Error> {
+            count += 1;
+            Err(Error::msg(format!("attempt {} failed", count)).transient())
+        })
+        .unwrap_err();
+
+        assert_eq!(error.to_string(), "gave up after 3 attempts");
+        assert_eq!(
+            error.downcast_ref::<Attempts>().map(Attempts::count),
+            Some(3)
+        );
+        let report = format!("{:?}", error);
+        let previous = report
+            .find("Previous attempts:")
+            .expect("earlier attempts in the report");
+        assert!(report[previous..].contains("1: attempt 1 failed"));
+        assert!(report[previous..].contains("2: attempt 2 failed"));
+    }
+
+    #[test]
+    fn attempts_of_a_child_are_listed_once_in_its_own_report() {
+        let policy = RetryPolicy::fixed(Duration::ZERO).max_attempts(2);
+        let child = retry(policy, || -> Result<(), Error> {
+            Err(Error::msg("flaky").transient())
+        })
+        .unwrap_err();
+        let mut multi = MultiError::new();
+        multi.push(child);
+        multi.push(Error::msg("other"));
+        let error = Error::from(multi);
+
+        assert!(!error.is::<Attempts>());
+        let report = format!("{:?}", error);
+        assert_eq!(report.matches("Previous attempts:").count(), 1);
+        let errors = report.find("Errors:").unwrap();
+        let previous = report.find("Previous attempts:").unwrap();
+        let other = report.find("1: other").unwrap();
+        assert!(errors < previous && previous < other);
+    }
+
+    #[test]
+    fn exponential_backoff_grows_by_the_factor() {
+        let (error, sleeps) = always_failing(RetryPolicy::exponential(MS).max_attempts(4));
+        assert_eq!(sleeps, [MS, 2 * MS, 4 * MS]);
+        assert_eq!(error.to_string(), "gave up after 4 attempts");
+
+        let policy = RetryPolicy::exponential(MS).factor(3).max_attempts(4);
+        let (_, sleeps) = always_failing(policy);
+        assert_eq!(sleeps, [MS, 3 * MS, 9 * MS]);
+
+        let policy = RetryPolicy::fixed(5 * MS).factor(3).max_attempts(3);
+        let (_, sleeps) = always_failing(policy);
+        assert_eq!(sleeps, [5 * MS, 5 * MS]);
+    }
+
+    #[test]
+    fn max_delay_caps_every_wait() {
+        let policy = RetryPolicy::exponential(MS)
+            .max_delay(3 * MS)
+            .max_attempts(5);
+        let (_, sleeps) = always_failing(policy);
+        assert_eq!(sleeps, [MS, 2 * MS, 3 * MS, 3 * MS]);
+
+        let policy = RetryPolicy::fixed(5 * MS).max_delay(2 * MS);
+        let (_, sleeps) = always_failing(policy);
+        assert_eq!(sleeps, [2 * MS, 2 * MS]);
+    }
+
+    #[test]
+    fn max_elapsed_gives_up_rather_than_wait_past_it() {
+        let policy = RetryPolicy::exponential(10 * MS)
+            .max_attempts(10)
+            .max_elapsed(35 * MS);
+        let (error, sleeps) = always_failing(policy);
+        // A third wait of 40ms would end at 70ms.
+        assert_eq!(sleeps, [10 * MS, 20 * MS]);
+        assert_eq!(error.to_string(), "ran out of time after 3 attempts");
+        assert_eq!(attempts(&error).count(), 3);
+        assert!(!attempts(&error).is_permanent());
+    }
+
+    #[test]
+    fn retry_if_retries_errors_without_the_transient_marker() {
+        let interrupted = |error: &(dyn std::error::Error + 'static)| {
+            error
+                .downcast_ref::<io::Error>()
+                .is_some_and(|error| error.kind() == io::ErrorKind::Interrupted)
+        };
+
+        let clock = FakeClock::new();
+        let policy = RetryPolicy::fixed(MS)
+            .retry_if(interrupted)
+            .clock(clock.clone());
+        let mut calls = 0;
+        let value = retry(policy, || {
+            calls += 1;
+            if calls == 1 {
+                Err(Error::from(io::Error::from(io::ErrorKind::Interrupted)).context("reading"))
+            } else {
+                Ok(calls)
+            }
+        })
+        .unwrap();
+        assert_eq!(value, 2);
+        assert_eq!(clock.sleeps(), [MS]);
+
+        let clock = FakeClock::new();
+        let policy = RetryPolicy::fixed(MS)
+            .retry_if(interrupted)
+            .clock(clock.clone());
+        let error = retry(policy, || -> Result<(), Error> {
+            Err(io::Error::from(io::ErrorKind::NotFound).into())
+        })
+        .unwrap_err();
+        assert!(attempts(&error).is_permanent());
+        assert!(clock.sleeps().is_empty());
+    }
+
+    #[test]
+    fn permanent_error_is_not_retried() {
+        let clock = FakeClock::new();
+        let policy = RetryPolicy::fixed(MS).max_attempts(5).clock(clock.clone());
+        let mut calls = 0;
+        let error = retry(policy, || -> Result<(), Error> {
+            calls += 1;
+            Err(Error::msg("bad input"))
+        })
+        .unwrap_err();
+        assert_eq!(calls, 1);
+        assert!(clock.sleeps().is_empty());
+        assert_eq!(error.to_string(), "permanent error after 1 attempt");
+        assert!(attempts(&error).is_permanent());
+        assert!(attempts(&error).previous().is_empty());
+        assert!(!format!("{:?}", error).contains("Previous attempts:"));
+
+        let clock = FakeClock::new();
+        let policy = RetryPolicy::fixed(MS).max_attempts(5).clock(clock.clone());
+        let mut calls = 0;
+        let error = retry(policy, || -> Result<(), Error> {
+            calls += 1;
+            match calls {
+                1 => Err(Error::msg("flaky").transient()),
+                _ => Err(Error::msg("bad input")),
+            }
+        })
+        .unwrap_err();
+        assert_eq!(calls, 2);
+        assert_eq!(clock.sleeps(), [MS]);
+        assert!(attempts(&error).is_permanent());
+        assert_eq!(attempts(&error).count(), 2);
+        assert_eq!(
+            format!("{:#}", error),
+            "permanent error after 2 attempts: bad input"
+        );
+    }
+}
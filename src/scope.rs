@@ -0,0 +1,209 @@
+use std::{
+    cell::{Cell, RefCell},
+    fmt::Display,
+    marker::PhantomData,
+    panic::Location,
+    rc::Rc,
+};
+
+use crate::Error;
+
+type Wrap = Rc<dyn Fn(Error) -> Error>;
+
+thread_local! {
+    static SCOPES: RefCell<Vec<(usize, Wrap)>> = const { RefCell::new(Vec::new()) };
+    static NEXT_ID: Cell<usize> = const { Cell::new(0) };
+    // Set while the scopes are being applied, so that an error created by a
+    // scope's own closure is left alone instead of recursing.
+    static APPLYING: Cell<bool> = const { Cell::new(false) };
+}
+
+/// Add context to every [`Error`] created on this thread until the returned
+/// guard is dropped. The closure must be `'static`, so write it as a `move`
+/// closure: `regardless::scope(move || format!("order {id}"))`.
+///
+/// The closure cannot borrow from the enclosing block, because a guard that
+/// is leaked with `mem::forget` would leave it on the stack after the
+/// borrow ends.
+///
+/// `context` only runs when an error is actually created: by `From` (and so
+/// `?`), by the macros, or by [`Context`](crate::Context) on a `Result` or
+/// `Option`. Adding context to an existing `Error` does not apply the scopes
+/// again, and neither does building an error around one that already has
+/// them, such as a [`MultiError`](crate::MultiError) of errors created in the
+/// scope. Nested scopes wrap the error innermost first, so the outermost scope
+/// ends up as the headline.
+#[track_caller]
+pub fn scope<C, F>(context: F) -> ScopeGuard
+where
+    C: Display + Send + Sync + 'static,
+    F: Fn() -> C + 'static,
+{
+    let location = Location::caller();
+    let id = NEXT_ID
+        .try_with(|next| next.replace(next.get() + 1))
+        .unwrap_or(usize::MAX);
+    let wrap: Wrap = Rc::new(move |error: Error| error.context(context()).with_location(location));
+    // While the thread is shutting down the scope is dropped instead.
+    let _ = SCOPES.try_with(|scopes| scopes.borrow_mut().push((id, wrap)));
+    ScopeGuard {
+        id,
+        _not_send: PhantomData,
+    }
+}
+
+/// Removes its scope when dropped. Returned by [`scope`].
+#[must_use = "the scope ends as soon as the guard is dropped"]
+pub struct ScopeGuard {
+    id: usize,
+    // The scope lives in a thread-local, so the guard has to stay on the
+    // thread that made it.
+    _not_send: PhantomData<*const ()>,
+}
+
+impl Drop for ScopeGuard {
+    fn drop(&mut self) {
+        let _ = SCOPES.try_with(|scopes| scopes.borrow_mut().retain(|(id, _)| *id != self.id));
+    }
+}
+
+// An error created while the thread-locals are being destroyed gets no
+// scopes.
+pub(crate) fn apply(error: Error) -> Error {
+    if APPLYING.try_with(Cell::get).unwrap_or(true) {
+        return error;
+    }
+    // Clone the stack out so the closures run without the RefCell borrowed.
+    let scopes: Vec<Wrap> = SCOPES
+        .try_with(|scopes| {
+            scopes
+                .borrow()
+                .iter()
+                .rev()
+                .map(|(_, wrap)| Rc::clone(wrap))
+                .collect()
+        })
+        .unwrap_or_default();
+    if scopes.is_empty() || error.is_scoped() {
+        return error;
+    }
+
+    APPLYING.with(|applying| applying.set(true));
+    let _reset = Reset;
+    scopes
+        .iter()
+        .fold(error, |error, wrap| wrap(error))
+        .mark_scoped()
+}
+
+struct Reset;
+
+impl Drop for Reset {
+    fn drop(&mut self) {
+        let _ = APPLYING.try_with(|applying| applying.set(false));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::Cell, error::Error as StdError, fmt, rc::Rc, thread};
+
+    use super::scope;
+    use crate::{Compat, Error, MultiError};
+
+    #[test]
+    fn context_is_only_evaluated_when_an_error_is_created() {
+        let calls = Rc::new(Cell::new(0));
+        let counted = Rc::clone(&calls);
+        let _guard = scope(move || {
+            counted.set(counted.get() + 1);
+            "scoped"
+        });
+        assert_eq!(calls.get(), 0);
+
+        let error = Error::msg("root");
+        assert_eq!(calls.get(), 1);
+        assert_eq!(format!("{:#}", error), "scoped: root");
+
+        let error = error.context("outer");
+        assert_eq!(calls.get(), 1);
+        assert_eq!(format!("{:#}", error), "outer: scoped: root");
+    }
+
+    #[test]
+    fn outermost_scope_is_the_headline() {
+        let _outer = scope(|| "outer");
+        let _inner = scope(|| "inner");
+        assert_eq!(format!("{:#}", Error::msg("root")), "outer: inner: root");
+    }
+
+    #[test]
+    fn dropping_the_guard_removes_its_scope() {
+        let outer = scope(|| "outer");
+        let inner = scope(|| "inner");
+        drop(outer);
+        assert_eq!(format!("{:#}", Error::msg("root")), "inner: root");
+        drop(inner);
+        assert_eq!(format!("{:#}", Error::msg("root")), "root");
+    }
+
+    #[test]
+    fn errors_that_already_have_the_scopes_do_not_get_them_again() {
+        let id = 1;
+        let _guard = scope(move || format!("order {id}"));
+
+        let mut multi = MultiError::new();
+        multi.push(Error::msg("x"));
+        let error = multi.into_result().unwrap_err();
+        assert_eq!(format!("{:#}", error), "1 error occurred");
+        let child = error
+            .downcast_ref::<MultiError>()
+            .unwrap()
+            .iter()
+            .next()
+            .unwrap();
+        assert_eq!(format!("{:#}", child), "order 1: x");
+
+        let error = Error::from(Wrapper(Error::msg("x").into_std()));
+        assert_eq!(format!("{:#}", error), "wrapper: order 1: x");
+    }
+
+    #[test]
+    fn errors_created_during_thread_shutdown_get_no_scopes() {
+        struct Late;
+
+        impl Drop for Late {
+            fn drop(&mut self) {
+                let _guard = scope(|| "too late");
+                assert_eq!(format!("{:#}", Error::msg("root")), "root");
+            }
+        }
+
+        thread_local! {
+            static LATE: Late = const { Late };
+        }
+
+        thread::spawn(|| {
+            // Registered first, so destroyed after the scope stack.
+            LATE.with(|_| {});
+            drop(scope(|| "scoped"));
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[derive(Debug)]
+    struct Wrapper(Compat);
+
+    impl fmt::Display for Wrapper {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str("wrapper")
+        }
+    }
+
+    impl StdError for Wrapper {
+        fn source(&self) -> Option<&(dyn StdError + 'static)> {
+            Some(&self.0)
+        }
+    }
+}
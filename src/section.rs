@@ -0,0 +1,186 @@
+use std::fmt::{self, Display};
+
+use crate::Error;
+
+/// Attach notes, suggestions, warnings and free-form sections to an error.
+///
+/// These are kept apart from the cause chain: they do not show up in
+/// `chain()` or `{:#}`, and the report renders them after the causes, in the
+/// order they were added.
+pub trait Section<T> {
+    fn note<D>(self, note: D) -> Result<T, Error>
+    where
+        D: Display + Send + Sync + 'static;
+    fn suggestion<D>(self, suggestion: D) -> Result<T, Error>
+    where
+        D: Display + Send + Sync + 'static;
+    fn warning<D>(self, warning: D) -> Result<T, Error>
+    where
+        D: Display + Send + Sync + 'static;
+    fn section<H, D>(self, title: H, body: D) -> Result<T, Error>
+    where
+        H: Display + Send + Sync + 'static,
+        D: Display + Send + Sync + 'static;
+}
+
+impl<T, E> Section<T> for Result<T, E>
+where
+    E: Into<Error>,
+{
+    #[track_caller]
+    fn note<D>(self, note: D) -> Result<T, Error>
+    where
+        D: Display + Send + Sync + 'static,
+    {
+        match self {
+            Ok(ok) => Ok(ok),
+            Err(error) => Err(error.into().note(note)),
+        }
+    }
+
+    #[track_caller]
+    fn suggestion<D>(self, suggestion: D) -> Result<T, Error>
+    where
+        D: Display + Send + Sync + 'static,
+    {
+        match self {
+            Ok(ok) => Ok(ok),
+            Err(error) => Err(error.into().suggestion(suggestion)),
+        }
+    }
+
+    #[track_caller]
+    fn warning<D>(self, warning: D) -> Result<T, Error>
+    where
+        D: Display + Send + Sync + 'static,
+    {
+        match self {
+            Ok(ok) => Ok(ok),
+            Err(error) => Err(error.into().warning(warning)),
+        }
+    }
+
+    #[track_caller]
+    fn section<H, D>(self, title: H, body: D) -> Result<T, Error>
+    where
+        H: Display + Send + Sync + 'static,
+        D: Display + Send + Sync + 'static,
+    {
+        match self {
+            Ok(ok) => Ok(ok),
+            Err(error) => Err(error.into().section(title, body)),
+        }
+    }
+}
+
+impl Error {
+    /// Add a note to the report.
+    pub fn note<D>(self, note: D) -> Self
+    where
+        D: Display + Send + Sync + 'static,
+    {
+        self.with_section(Heading::Note, Box::new(note))
+    }
+
+    /// Add a suggestion for how to fix the error to the report.
+    pub fn suggestion<D>(self, suggestion: D) -> Self
+    where
+        D: Display + Send + Sync + 'static,
+    {
+        self.with_section(Heading::Suggestion, Box::new(suggestion))
+    }
+
+    /// Add a warning to the report.
+    pub fn warning<D>(self, warning: D) -> Self
+    where
+        D: Display + Send + Sync + 'static,
+    {
+        self.with_section(Heading::Warning, Box::new(warning))
+    }
+
+    /// Add a section with its own title to the report. The body is rendered
+    /// indented below the title.
+    pub fn section<H, D>(self, title: H, body: D) -> Self
+    where
+        H: Display + Send + Sync + 'static,
+        D: Display + Send + Sync + 'static,
+    {
+        self.with_section(Heading::Custom(Box::new(title)), Box::new(body))
+    }
+}
+
+pub(crate) struct ReportSection {
+    pub(crate) heading: Heading,
+    pub(crate) body: Box<dyn Display + Send + Sync>,
+}
+
+pub(crate) enum Heading {
+    Note,
+    Suggestion,
+    Warning,
+    Custom(Box<dyn Display + Send + Sync>),
+}
+
+impl Display for Heading {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Heading::Note => f.write_str("Note"),
+            Heading::Suggestion => f.write_str("Suggestion"),
+            Heading::Warning => f.write_str("Warning"),
+            Heading::Custom(title) => Display::fmt(title, f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use super::Section;
+    use crate::Error;
+
+    // The sections of the `{:?}` report, without the backtrace.
+    fn sections(error: &Error) -> String {
+        let report = format!("{:?}", error);
+        let report = report.split("\n\nStack backtrace:").next().unwrap();
+        let start = report.find("\n\n").map_or(report.len(), |i| i + 2);
+        report[start..]
+            .split("\n\n")
+            .filter(|part| !part.starts_with("Caused by:"))
+            .collect::<Vec<_>>()
+            .join("\n\n")
+    }
+
+    #[test]
+    fn sections_render_in_the_order_they_were_added_across_layers() {
+        let error = Error::msg("root")
+            .note("first")
+            .context("outer")
+            .warning("second")
+            .suggestion("third");
+        assert_eq!(
+            sections(&error),
+            "Note: first\n\nWarning: second\n\nSuggestion: third"
+        );
+    }
+
+    #[test]
+    fn short_labels_hang_and_custom_titles_indent() {
+        let error = Error::msg("root")
+            .note("line one\nline two")
+            .section("Details", "a\nb");
+        assert_eq!(
+            sections(&error),
+            "Note: line one\n      line two\n\nDetails:\n    a\n    b"
+        );
+    }
+
+    #[test]
+    fn sections_stay_out_of_the_chain() {
+        let error = Err::<(), _>(io::Error::other("root"))
+            .note("a note")
+            .unwrap_err();
+        assert_eq!(format!("{:#}", error), "root");
+        assert_eq!(error.chain().count(), 1);
+    }
+}
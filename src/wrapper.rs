@@ -0,0 +1,75 @@
+use std::{
+    error::Error as StdError,
+    fmt::{self, Debug, Display},
+};
+
+// Adapts a plain message into an error. `repr(transparent)` so the message can
+// be downcast to directly.
+#[repr(transparent)]
+pub(crate) struct MessageError<M>(pub(crate) M);
+
+impl<M> Display for MessageError<M>
+where
+    M: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl<M> Debug for MessageError<M>
+where
+    M: Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl<M> StdError for MessageError<M> where M: Display + Debug {}
+
+// Like `MessageError`, for values that are only `Display`.
+#[repr(transparent)]
+pub(crate) struct DisplayError<M>(pub(crate) M);
+
+impl<M> Display for DisplayError<M>
+where
+    M: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl<M> Debug for DisplayError<M>
+where
+    M: Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl<M> StdError for DisplayError<M> where M: Display {}
+
+// Lets an already boxed error be wrapped without losing its `source()` chain.
+#[repr(transparent)]
+pub(crate) struct BoxedError(pub(crate) Box<dyn StdError + Send + Sync>);
+
+impl Display for BoxedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl Debug for BoxedError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl StdError for BoxedError {
+    fn source(&self) -> Option<&(dyn StdError + 'static)> {
+        self.0.source()
+    }
+}
@@ -0,0 +1,88 @@
+// The hook is process-wide and can only be installed once, so these tests
+// live in their own binary.
+
+use std::{cell::Cell, fmt, io, sync::Once};
+
+use regardless::{regardless, set_hook, DefaultHandler, Error, ReportHandler};
+
+thread_local! {
+    // Tests run on their own threads, so counting per thread keeps them
+    // independent.
+    static CREATED: Cell<usize> = const { Cell::new(0) };
+}
+
+struct Tagged(usize);
+
+impl ReportHandler for Tagged {
+    fn debug(&self, error: &Error, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[handler {}] ", self.0)?;
+        DefaultHandler.debug(error, f)
+    }
+}
+
+fn install() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        set_hook(Box::new(|_| {
+            let id = CREATED.with(|created| {
+                created.set(created.get() + 1);
+                created.get()
+            });
+            Box::new(Tagged(id))
+        }))
+        .unwrap();
+    });
+}
+
+fn created() -> usize {
+    CREATED.with(Cell::get)
+}
+
+#[test]
+fn hook_runs_once_per_created_error() {
+    install();
+    let before = created();
+
+    let error = Error::msg("root");
+    assert_eq!(created(), before + 1);
+
+    let error = error.context("middle").context("outer");
+    assert_eq!(created(), before + 1);
+
+    let _other = regardless!(io::Error::other("io"));
+    assert_eq!(created(), before + 2);
+    drop(error);
+}
+
+#[test]
+fn context_layers_use_the_handler_of_the_error_they_wrap() {
+    install();
+    let error = Error::msg("root");
+    let id = created();
+
+    let error = error.context("outer");
+    assert!(error.handler().is_some());
+    assert!(format!("{:?}", error).starts_with(&format!("[handler {}] outer", id)));
+}
+
+#[test]
+fn delegating_to_the_default_handler_does_not_recurse() {
+    install();
+    let error = Error::msg("root").context("outer");
+    let report = format!("{:?}", error);
+    let report = report.split("\n\nStack backtrace:").next().unwrap();
+
+    let lines: Vec<_> = report
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("at "))
+        .collect();
+    assert_eq!(lines.len(), 4);
+    assert!(lines[0].ends_with("] outer"));
+    assert_eq!(lines[1..], ["", "Caused by:", "    root"]);
+}
+
+#[test]
+fn second_install_fails() {
+    install();
+    assert!(set_hook(Box::new(|_| Box::new(DefaultHandler))).is_err());
+}
@@ -0,0 +1,99 @@
+// Every way of creating an error or adding a layer must record the caller's
+// location, not one inside the crate. Each error below is created on the
+// line after `line!() + 1`.
+
+use std::{io, panic::Location};
+
+use regardless::{bail, ensure, regardless, Context, Error, Result, Section};
+
+fn assert_here(location: &Location<'_>, line: u32) {
+    assert_eq!(location.file(), file!());
+    assert_eq!(location.line(), line);
+}
+
+fn not_found() -> io::Result<()> {
+    Err(io::Error::from(io::ErrorKind::NotFound))
+}
+
+#[test]
+fn question_mark() {
+    let mut line = 0;
+    let mut run = || -> Result<()> {
+        line = line!() + 1;
+        not_found()?;
+        Ok(())
+    };
+    let error = run().unwrap_err();
+    assert_here(error.location(), line);
+}
+
+#[test]
+fn regardless_macro() {
+    let line = line!() + 1;
+    let error = regardless!("message");
+    assert_here(error.location(), line);
+
+    let line = line!() + 1;
+    let error = regardless!(io::Error::other("io"));
+    assert_here(error.location(), line);
+}
+
+#[test]
+fn bail_macro() {
+    let mut line = 0;
+    let mut run = || -> Result<()> {
+        line = line!() + 1;
+        bail!("failed with {}", 1);
+    };
+    let error = run().unwrap_err();
+    assert_here(error.location(), line);
+}
+
+#[test]
+fn ensure_macro() {
+    let mut line = 0;
+    let mut run = |n: u32| -> Result<()> {
+        line = line!() + 1;
+        ensure!(n == 1);
+        Ok(())
+    };
+    let error = run(2).unwrap_err();
+    assert_here(error.location(), line);
+}
+
+#[test]
+fn context_on_result() {
+    let line = line!() + 1;
+    let error = not_found().context("outer").unwrap_err();
+    assert_here(error.location(), line);
+
+    let line = line!() + 1;
+    let error = not_found().with_context(|| "outer").unwrap_err();
+    assert_here(error.location(), line);
+}
+
+#[test]
+fn context_on_option() {
+    let line = line!() + 1;
+    let error = None::<()>.context("missing").unwrap_err();
+    assert_here(error.location(), line);
+
+    let line = line!() + 1;
+    let error = None::<()>.with_context(|| "missing").unwrap_err();
+    assert_here(error.location(), line);
+}
+
+#[test]
+fn extend_context() {
+    let mut error = Error::msg("root");
+    let line = line!() + 1;
+    error.extend_context("outer");
+    assert_here(error.location(), line);
+}
+
+#[test]
+fn section_on_foreign_result() {
+    let line = line!() + 1;
+    let error = not_found().note("check the path").unwrap_err();
+    assert_here(error.location(), line);
+}